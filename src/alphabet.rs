@@ -0,0 +1,423 @@
+use crate::stats::ENGLISH_FREQUENCIES;
+use crate::Char;
+use lazy_static::lazy_static;
+use simple_error::SimpleError;
+use smallvec::{smallvec, SmallVec};
+use std::sync::Arc;
+
+lazy_static! {
+    static ref ENGLISH: Arc<Alphabet> = Arc::new(Alphabet {
+        symbols: (b'A'..=b'Z').map(char::from).collect(),
+        case: Case::Fold,
+        frequencies: ENGLISH_FREQUENCIES.to_vec(),
+        aliases: Vec::new(),
+        policy: Policy::Reject,
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    // Upper and lower case are the same symbol, and text keeps its case on the way out
+    Fold,
+    // Upper and lower case are different symbols
+    Sensitive,
+}
+
+// Accented and other letters written with the plain letters they are usually taken for. The
+// German umlauts lose their dots rather than gaining an E.
+const TRANSLITERATIONS: &[(&str, &str)] = &[
+    ("ÀÁÂÃÄÅĀĂĄ", "A"),
+    ("Æ", "AE"),
+    ("ÇĆĈĊČ", "C"),
+    ("ÐĎĐ", "D"),
+    ("ÈÉÊËĒĔĖĘĚ", "E"),
+    ("ĜĞĠĢ", "G"),
+    ("ĤĦ", "H"),
+    ("ÌÍÎÏĨĪĬĮİ", "I"),
+    ("Ĳ", "IJ"),
+    ("Ĵ", "J"),
+    ("Ķ", "K"),
+    ("ĹĻĽĿŁ", "L"),
+    ("ÑŃŅŇ", "N"),
+    ("ÒÓÔÕÖØŌŎŐ", "O"),
+    ("Œ", "OE"),
+    ("ŔŖŘ", "R"),
+    ("ŚŜŞŠ", "S"),
+    ("ß", "SS"),
+    ("ŢŤŦ", "T"),
+    ("Þ", "TH"),
+    ("ÙÚÛÜŨŪŬŮŰŲ", "U"),
+    ("Ŵ", "W"),
+    ("ÝŶŸ", "Y"),
+    ("ŹŻŽ", "Z"),
+];
+
+// What becomes of letters that are not in the alphabet when text is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    // They are an error
+    Reject,
+    // They are left out of the text altogether
+    Strip,
+    // They stay in the text untouched, like punctuation
+    Keep,
+    // They are read as the plain letters of `TRANSLITERATIONS`, and any without one are an error
+    Transliterate,
+}
+
+// How a single character of text is read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reading {
+    // As these symbols of the alphabet, more than one for a transliterated ß or æ
+    Symbols(SmallVec<[Char; 2]>),
+    // Not read, but left in the text like punctuation
+    Kept,
+    // Left out of the text altogether
+    Dropped,
+}
+
+// The letter left out of the alphabet, to fit the rest in a 5x5 square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Merge {
+    // J is read as I
+    IJ,
+    // Q is left out of the letters read, though text written back keeps it
+    OmitQ,
+    // W is read as V
+    VW,
+}
+
+// The symbols a buffer is written in. A symbol's index in the alphabet is its value as a
+// `Char`, so shifting a character wraps round at the size of its alphabet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alphabet {
+    symbols: Vec<char>,
+    case: Case,
+    frequencies: Vec<f64>,
+    // Characters outside the alphabet that are read as one of its symbols, or dropped for None
+    aliases: Vec<(char, Option<char>)>,
+    policy: Policy,
+}
+
+// Only folds characters with a single character upper case, so that 'ß' stays as it is
+fn fold(c: char) -> char {
+    let mut upper = c.to_uppercase();
+
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+impl Alphabet {
+    // Every symbol is taken as equally likely until `with_frequencies` says otherwise
+    pub fn new(symbols: &str, case: Case) -> Result<Self, SimpleError> {
+        let symbols = symbols
+            .chars()
+            .map(|c| if case == Case::Fold { fold(c) } else { c })
+            .collect::<Vec<_>>();
+
+        if symbols.len() < 2 || symbols.len() > usize::from(u8::MAX) {
+            return Err(SimpleError::new("alphabet must have 2 to 255 symbols"));
+        }
+
+        for (i, &c) in symbols.iter().enumerate() {
+            if c.is_whitespace() {
+                return Err(SimpleError::new("alphabet contains whitespace"));
+            }
+
+            if symbols[..i].contains(&c) {
+                return Err(SimpleError::new(format!("symbol '{}' is used twice", c)));
+            }
+        }
+
+        let frequencies = vec![1.0 / symbols.len() as f64; symbols.len()];
+        Ok(Self {
+            symbols,
+            case,
+            frequencies,
+            aliases: Vec::new(),
+            policy: Policy::Reject,
+        })
+    }
+
+    // The 26 letters, for which every cipher is written
+    pub fn english() -> Arc<Self> {
+        ENGLISH.clone()
+    }
+
+    // The letters followed by the digits, as used by ADFGVX and the 6x6 squares
+    pub fn alphanumeric() -> Self {
+        Self::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", Case::Fold).unwrap()
+    }
+
+    // The 25 letters of the square ciphers, with English frequencies. The letter left out is
+    // still read from text, as its partner or not at all.
+    pub fn twenty_five(merge: Merge) -> Self {
+        let (from, to) = match merge {
+            Merge::IJ => ('J', Some('I')),
+            Merge::OmitQ => ('Q', None),
+            Merge::VW => ('W', Some('V')),
+        };
+
+        let english = Self::english();
+        let mut frequencies = english.frequencies.clone();
+        let i = english.symbols.iter().position(|&c| c == from).unwrap();
+
+        if let Some(to) = to {
+            let j = english.symbols.iter().position(|&c| c == to).unwrap();
+            frequencies[j] += frequencies[i];
+        }
+        frequencies.remove(i);
+
+        let symbols = english.symbols.iter().filter(|&&c| c != from);
+        Self::new(&symbols.collect::<String>(), Case::Fold)
+            .and_then(|a| a.with_frequencies(&frequencies))
+            .and_then(|a| a.with_alias(from, to))
+            .unwrap()
+    }
+
+    // Reads a character that is not in the alphabet as one of its symbols, or drops it from the
+    // text if `to` is None
+    pub fn with_alias(mut self, from: char, to: Option<char>) -> Result<Self, SimpleError> {
+        let from = if self.case == Case::Fold {
+            fold(from)
+        } else {
+            from
+        };
+
+        if self.index(from).is_some() || self.drops(from) {
+            return Err(SimpleError::new(format!("'{}' is already read", from)));
+        }
+
+        let to = match to {
+            Some(to) => match self.index(to) {
+                Some(c) => Some(self.symbol(c)),
+                None => return Err(SimpleError::new(format!("'{}' is not in the alphabet", to))),
+            },
+            None => None,
+        };
+
+        self.aliases.push((from, to));
+        Ok(self)
+    }
+
+    // Letters outside the alphabet are rejected unless this says otherwise
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // The expected frequency of each symbol in plaintext, used by `stats`. They are scaled to
+    // add up to one.
+    pub fn with_frequencies(mut self, frequencies: &[f64]) -> Result<Self, SimpleError> {
+        if frequencies.len() != self.symbols.len() {
+            return Err(SimpleError::new(format!(
+                "expected {} frequencies",
+                self.symbols.len()
+            )));
+        }
+
+        if frequencies.iter().any(|&f| f <= 0.0 || f.is_nan()) {
+            return Err(SimpleError::new("frequencies must be positive"));
+        }
+
+        let total = frequencies.iter().sum::<f64>();
+        self.frequencies = frequencies.iter().map(|f| f / total).collect();
+        Ok(self)
+    }
+
+    pub fn size(&self) -> u8 {
+        self.symbols.len() as u8
+    }
+
+    pub fn case(&self) -> Case {
+        self.case
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    pub fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    pub fn is_english(&self) -> bool {
+        self.case == Case::Fold && self.symbols == ENGLISH.symbols
+    }
+
+    fn alias(&self, c: char) -> Option<Option<char>> {
+        self.aliases.iter().find(|a| a.0 == c).map(|a| a.1)
+    }
+
+    pub fn index(&self, c: char) -> Option<Char> {
+        let c = if self.case == Case::Fold { fold(c) } else { c };
+        let c = self.alias(c).unwrap_or(Some(c))?;
+
+        self.symbols
+            .iter()
+            .position(|&s| s == c)
+            .map(|i| Char::new(i as u8, self.size()))
+    }
+
+    // Whether the character is left out of text read in this alphabet
+    pub fn drops(&self, c: char) -> bool {
+        self.read(c).ok() == Some(Reading::Dropped)
+    }
+
+    // Whether the character is the letter left out of the alphabet by an alias, rather than
+    // stripped for not being in it
+    pub fn omits(&self, c: char) -> bool {
+        let folded = if self.case == Case::Fold { fold(c) } else { c };
+        self.index(c).is_none() && self.alias(folded) == Some(None)
+    }
+
+    pub fn read(&self, c: char) -> Result<Reading, SimpleError> {
+        if let Some(c) = self.index(c) {
+            return Ok(Reading::Symbols(smallvec![c]));
+        }
+
+        let folded = if self.case == Case::Fold { fold(c) } else { c };
+        if self.alias(folded) == Some(None) {
+            return Ok(Reading::Dropped);
+        }
+
+        if !c.is_alphabetic() {
+            return Ok(Reading::Kept);
+        }
+
+        match self.policy {
+            Policy::Reject => Err(SimpleError::new(format!("'{}' is not in the alphabet", c))),
+            Policy::Strip => Ok(Reading::Dropped),
+            Policy::Keep => Ok(Reading::Kept),
+            Policy::Transliterate => {
+                let plain = TRANSLITERATIONS
+                    .iter()
+                    .find(|t| t.0.contains(fold(c)))
+                    .map(|t| t.1)
+                    .ok_or_else(|| SimpleError::new(format!("no transliteration for '{}'", c)))?;
+
+                let plain = if c.is_lowercase() {
+                    plain.to_lowercase()
+                } else {
+                    plain.to_owned()
+                };
+
+                plain
+                    .chars()
+                    .map(|p| match self.index(p) {
+                        Some(p) => Ok(p),
+                        None => Err(SimpleError::new(format!("no transliteration for '{}'", c))),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Reading::Symbols)
+            }
+        }
+    }
+
+    pub fn symbol(&self, c: Char) -> char {
+        self.symbols[usize::from(u8::from(c))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_symbol() {
+        let alphabet = Alphabet::alphanumeric();
+        assert_eq!(36, alphabet.size());
+
+        let c = alphabet.index('7').unwrap();
+        assert_eq!(33, u8::from(c));
+        assert_eq!('7', alphabet.symbol(c));
+        assert_eq!('B', alphabet.symbol(c + 4));
+        assert_eq!(alphabet.index('Q'), alphabet.index('q'));
+        assert_eq!(None, alphabet.index('-'));
+
+        let alphabet = Alphabet::new("aAbB", Case::Sensitive).unwrap();
+        assert_eq!(Some(Char::new(1, 4)), alphabet.index('A'));
+        assert_eq!(None, alphabet.index('c'));
+    }
+
+    #[test]
+    fn test_english() {
+        let english = Alphabet::english();
+        assert!(english.is_english());
+        assert!(!Alphabet::alphanumeric().is_english());
+        assert_eq!(Some(Char::from('k')), english.index('k'));
+        assert!((english.frequencies().iter().sum::<f64>() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_twenty_five() {
+        let alphabet = Alphabet::twenty_five(Merge::IJ);
+        assert_eq!(25, alphabet.size());
+        assert_eq!(alphabet.index('I'), alphabet.index('j'));
+        assert_eq!(Some('K'), alphabet.index('K').map(|c| alphabet.symbol(c)));
+        assert!(!alphabet.drops('J'));
+
+        let english = Alphabet::english();
+        let expected = english.frequencies()[8] + english.frequencies()[9];
+        assert!((alphabet.frequencies()[8] - expected).abs() < 1e-3);
+
+        let alphabet = Alphabet::twenty_five(Merge::OmitQ);
+        assert_eq!(None, alphabet.index('q'));
+        assert!(alphabet.drops('q'));
+        assert!(alphabet.omits('q'));
+        assert!(!alphabet.omits('!'));
+
+        let alphabet = Alphabet::twenty_five(Merge::VW);
+        assert_eq!(alphabet.index('V'), alphabet.index('W'));
+        assert!(alphabet.with_alias('V', None).is_err());
+    }
+
+    #[test]
+    fn test_policy() {
+        let english = (*Alphabet::english()).clone();
+        let read = |c| english.read(c).unwrap();
+        assert_eq!(Reading::Symbols(smallvec![Char::from('E')]), read('e'));
+        assert_eq!(Reading::Kept, read('!'));
+        assert!(english.read('é').is_err());
+
+        let strip = english.clone().with_policy(Policy::Strip);
+        assert_eq!(Reading::Dropped, strip.read('é').unwrap());
+
+        let keep = english.clone().with_policy(Policy::Keep);
+        assert_eq!(Reading::Kept, keep.read('é').unwrap());
+
+        let transliterate = english.with_policy(Policy::Transliterate);
+        let ss = smallvec![Char::from('S'), Char::from('S')];
+        assert_eq!(Reading::Symbols(ss), transliterate.read('ß').unwrap());
+        assert_eq!(
+            Reading::Symbols(smallvec![Char::from('O')]),
+            transliterate.read('Ø').unwrap()
+        );
+        assert!(transliterate.read('Ж').is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Alphabet::new("A", Case::Fold).is_err());
+        assert!(Alphabet::new("AB C", Case::Fold).is_err());
+        assert!(Alphabet::new("ABa", Case::Fold).is_err());
+        assert!(Alphabet::new("ABa", Case::Sensitive).is_ok());
+
+        let alphabet = Alphabet::new("AB", Case::Fold).unwrap();
+        assert!(alphabet.clone().with_frequencies(&[1.0]).is_err());
+        assert!(alphabet.clone().with_frequencies(&[1.0, 0.0]).is_err());
+        assert_eq!(
+            vec![0.75, 0.25],
+            alphabet
+                .with_frequencies(&[3.0, 1.0])
+                .unwrap()
+                .frequencies()
+        );
+    }
+}
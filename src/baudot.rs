@@ -0,0 +1,214 @@
+use crate::alphabet::Reading;
+use crate::{Buffer, Char, CharStream};
+use simple_error::SimpleError;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::{fmt, slice};
+
+// Each character is five impulses, the first held in the highest bit
+pub const IMPULSES: usize = 5;
+
+pub const SPACE: u8 = 0b00100;
+
+// The ITA2 codes of the letters
+const CODES: [u8; Char::MAX as usize] = [
+    0b11000, 0b10011, 0b01110, 0b10010, 0b10000, 0b10110, 0b01011, 0b00101, 0b01100, 0b11010,
+    0b11110, 0b01001, 0b00111, 0b00110, 0b00011, 0b01101, 0b11101, 0b01010, 0b10100, 0b00001,
+    0b11100, 0b01111, 0b11001, 0b10111, 0b10101, 0b10001,
+];
+
+// Every code written as one character, in the notation of Bletchley Park. The letters stand
+// for themselves, / is null, 9 space, 3 line feed, 4 carriage return, 5 figure shift and 8
+// letter shift.
+const NOTATION: &[u8; 32] = b"/T3O9HNM4LRGIPCVEZDBSYFXAWJ5UQK8";
+
+// A stream of five bit teleprinter characters, the form worked on by the Lorenz cipher
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BaudotBuffer {
+    data: Vec<u8>,
+}
+
+pub fn impulse(code: u8, i: usize) -> bool {
+    (code >> (IMPULSES - 1 - i)) & 1 == 1
+}
+
+impl BaudotBuffer {
+    pub fn new(data: Vec<u8>) -> Self {
+        assert!(data.iter().all(|&c| c < 32), "code out of range");
+
+        Self { data }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn push(&mut self, code: u8) {
+        assert!(code < 32, "code out of range");
+
+        self.data.push(code);
+    }
+
+    // Word breaks are taken from whitespace in the buffer's original text, and sent as spaces.
+    // The text is read with the buffer's alphabet to tell which characters are its letters.
+    pub fn encode(buf: &Buffer) -> Result<Self, SimpleError> {
+        if !buf.alphabet().is_english() {
+            return Err(SimpleError::new("Baudot code needs the English alphabet"));
+        }
+
+        let alphabet = buf.alphabet();
+        let mut out = BaudotBuffer::default();
+        let mut letters = buf.into_iter();
+        let mut word_break = false;
+
+        for c in buf.original().chars() {
+            if c.is_whitespace() {
+                word_break = true;
+            }
+
+            let count = match alphabet.read(c) {
+                Ok(Reading::Symbols(symbols)) => symbols.len(),
+                _ => continue,
+            };
+
+            for &letter in (&mut letters).take(count) {
+                if word_break && !out.is_empty() {
+                    out.push(SPACE);
+                }
+
+                out.push(CODES[usize::from(u8::from(letter))]);
+                word_break = false;
+            }
+        }
+
+        Ok(out)
+    }
+
+    // Spaces become word breaks, and the other characters that are not letters are dropped
+    pub fn decode(&self) -> Buffer {
+        let mut data = Vec::new();
+        let mut original = String::new();
+
+        for &code in &self.data {
+            match CODES.iter().position(|&c| c == code) {
+                Some(i) => {
+                    data.push(Char::from(i as u8));
+                    original.push(char::from(Char::from(i as u8)));
+                }
+                None if code == SPACE && !original.is_empty() => original.push(' '),
+                None => (),
+            }
+        }
+
+        Buffer::new(data, Arc::new(original))
+    }
+}
+
+impl<'a> IntoIterator for &'a BaudotBuffer {
+    type Item = &'a u8;
+    type IntoIter = slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+impl From<Vec<u8>> for BaudotBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        BaudotBuffer::new(data)
+    }
+}
+
+impl FromStr for BaudotBuffer {
+    type Err = SimpleError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        data.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                NOTATION
+                    .iter()
+                    .position(|&n| char::from(n) == c.to_ascii_uppercase())
+                    .map(|i| i as u8)
+                    .ok_or_else(|| SimpleError::new("string contains non-baudot characters"))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(BaudotBuffer::new)
+    }
+}
+
+impl From<&str> for BaudotBuffer {
+    fn from(data: &str) -> Self {
+        BaudotBuffer::from_str(data).unwrap()
+    }
+}
+
+impl fmt::Display for BaudotBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &code in &self.data {
+            write!(f, "{}", char::from(NOTATION[usize::from(code)]))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alphabet::Policy;
+    use crate::Alphabet;
+
+    #[test]
+    fn test_encode_decode() {
+        let baudot = BaudotBuffer::encode(&Buffer::from("Come at once")).unwrap();
+        assert_eq!("COME9AT9ONCE", baudot.to_string());
+        assert_eq!(0b01110, baudot.as_slice()[0]);
+
+        let buf = baudot.decode();
+        assert_eq!("COME AT ONCE", buf.to_string());
+    }
+
+    #[test]
+    fn test_policies() {
+        let english = (*Alphabet::english()).clone();
+
+        let keep = Arc::new(english.clone().with_policy(Policy::Keep));
+        let buf = Buffer::parse("Café au lait", keep).unwrap();
+        let baudot = BaudotBuffer::encode(&buf).unwrap();
+        assert_eq!("CAF AU LAIT", baudot.decode().to_string());
+
+        let transliterate = Arc::new(english.with_policy(Policy::Transliterate));
+        let buf = Buffer::parse("Große Straße", transliterate).unwrap();
+        let baudot = BaudotBuffer::encode(&buf).unwrap();
+        assert_eq!("GROSSE STRASSE", baudot.decode().to_string());
+    }
+
+    #[test]
+    fn test_notation() {
+        for (i, &c) in CODES.iter().enumerate() {
+            assert_eq!(b'A' + i as u8, NOTATION[usize::from(c)]);
+        }
+
+        let baudot = BaudotBuffer::from("/3458 HI");
+        assert_eq!(vec![0, 2, 8, 27, 31, 5, 12], baudot.as_slice().to_vec());
+        assert_eq!("HI", baudot.decode().to_string());
+        assert!(BaudotBuffer::from_str("A-B").is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Come at 9", alphanumeric).unwrap();
+
+        assert!(BaudotBuffer::encode(&buf).is_err());
+    }
+}
@@ -1,21 +1,90 @@
 use simple_error::SimpleError;
+use std::marker::PhantomData;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::{fmt, iter, slice};
 
+use crate::alphabet::{Alphabet, Case, Reading};
 use crate::{score, Char};
+use itertools::Itertools;
+use smallvec::SmallVec;
+
+// How a buffer is written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // In the layout of the original text, keeping its case and punctuation
+    Layout,
+    // The letters alone
+    Letters,
+    // The letters in groups of `size`, `per_line` groups to a line, as messages were sent
+    Groups { size: usize, per_line: usize },
+}
 
 #[derive(Clone)]
 pub struct Buffer {
     data: Vec<Char>,
     original: Arc<String>,
+    alphabet: Arc<Alphabet>,
 }
 
+// A view of some of the positions of a buffer, which ciphers and stats see as a buffer of its
+// own. Writes go straight through to the buffer underneath.
 #[derive(Clone, Debug)]
 pub struct PartialBuffer {
     buf: Buffer,
-    offset: usize,
-    stride: usize,
+    positions: Positions,
+}
+
+#[derive(Clone, Debug)]
+enum Positions {
+    Stride { offset: usize, stride: usize },
+    // Distinct indices into the buffer, in the order they are visited
+    Indices(Arc<[usize]>),
+}
+
+pub struct PartialIter<'a> {
+    inner: PartialIterInner<'a>,
+}
+
+enum PartialIterInner<'a> {
+    Stride(iter::StepBy<iter::Skip<slice::Iter<'a, Char>>>),
+    Indices(&'a [Char], slice::Iter<'a, usize>),
+}
+
+pub struct PartialIterMut<'a> {
+    inner: PartialIterMutInner<'a>,
+}
+
+enum PartialIterMutInner<'a> {
+    Stride(iter::StepBy<iter::Skip<slice::IterMut<'a, Char>>>),
+    // The indices were checked to be distinct and in bounds when the view was made, so no
+    // character is handed out twice
+    Indices(*mut Char, slice::Iter<'a, usize>, PhantomData<&'a mut Char>),
+}
+
+impl<'a> Iterator for PartialIter<'a> {
+    type Item = &'a Char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            PartialIterInner::Stride(it) => it.next(),
+            PartialIterInner::Indices(data, indices) => indices.next().map(|&i| &data[i]),
+        }
+    }
+}
+
+impl<'a> Iterator for PartialIterMut<'a> {
+    type Item = &'a mut Char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            PartialIterMutInner::Stride(it) => it.next(),
+            PartialIterMutInner::Indices(data, indices, _) => {
+                // Safe since each index is in bounds and is only visited once
+                indices.next().map(|&i| unsafe { &mut *data.add(i) })
+            }
+        }
+    }
 }
 
 pub trait IntoBorrowingIterator<'a> {
@@ -46,30 +115,72 @@ where
 pub trait CharStream<'a>: IntoBorrowingIterator<'a> {
     fn original(&'a self) -> &'a str;
 
+    fn alphabet(&'a self) -> &'a Alphabet;
+
     fn len(&'a self) -> usize;
 
+    fn is_empty(&'a self) -> bool {
+        self.len() == 0
+    }
+
     fn collect_original(&'a self) -> String {
+        let alphabet = self.alphabet();
         let mut char_stream = self.iter();
-        let mut next_char = char_stream.next();
-
-        self.original()
-            .chars()
-            .map(|c| {
-                if let Some(entry) = next_char {
-                    if c.is_alphabetic() {
-                        next_char = char_stream.next();
-
-                        if c.is_uppercase() {
-                            return entry.to_upper();
-                        } else {
-                            return entry.to_lower();
-                        };
-                    }
+        let mut out = String::with_capacity(self.original().len());
+
+        for c in self.original().chars() {
+            let symbols = match alphabet.read(c) {
+                Ok(Reading::Symbols(symbols)) => symbols,
+                // The letter a 25 letter alphabet leaves out is written back as it was, like
+                // punctuation, where stripped characters stay out
+                Ok(Reading::Dropped) if !alphabet.omits(c) => continue,
+                _ => {
+                    out.push(c);
+                    continue;
                 }
+            };
 
-                c
-            })
-            .collect::<String>()
+            let entries = (&mut char_stream)
+                .take(symbols.len())
+                .copied()
+                .collect::<SmallVec<[Char; 2]>>();
+
+            // Characters still reading as they did are written as they were, so a J read as I in
+            // a 25 letter alphabet is still a J, and a ß read as SS is still a ß
+            if entries.is_empty() || entries == symbols {
+                out.push(c);
+                continue;
+            }
+
+            for entry in entries {
+                let symbol = alphabet.symbol(entry);
+
+                if alphabet.case() == Case::Fold && c.is_lowercase() {
+                    out.extend(symbol.to_lowercase());
+                } else {
+                    out.push(symbol);
+                }
+            }
+        }
+
+        out
+    }
+
+    fn format(&'a self, format: Format) -> String {
+        let alphabet = self.alphabet();
+        let letters = self.iter().map(|&c| alphabet.symbol(c));
+
+        match format {
+            Format::Layout => self.collect_original(),
+            Format::Letters => letters.collect(),
+            Format::Groups { size, per_line } => {
+                let groups = letters.chunks(size);
+                let groups = groups.into_iter().map(|g| g.collect::<String>());
+                let lines = groups.chunks(per_line);
+
+                lines.into_iter().map(|mut l| l.join(" ")).join("\n")
+            }
+        }
     }
 }
 
@@ -93,47 +204,177 @@ impl<'a> IntoIterator for &'a mut Buffer {
 
 impl<'a> IntoIterator for &'a PartialBuffer {
     type Item = &'a Char;
-    type IntoIter = iter::StepBy<iter::Skip<<&'a Buffer as IntoIterator>::IntoIter>>;
+    type IntoIter = PartialIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.buf.data.iter().skip(self.offset).step_by(self.stride)
+        let inner = match &self.positions {
+            Positions::Stride { offset, stride } => {
+                PartialIterInner::Stride(self.buf.data.iter().skip(*offset).step_by(*stride))
+            }
+            Positions::Indices(indices) => {
+                PartialIterInner::Indices(&self.buf.data, indices.iter())
+            }
+        };
+
+        PartialIter { inner }
     }
 }
 
 impl<'a> IntoIterator for &'a mut PartialBuffer {
     type Item = &'a mut Char;
-    type IntoIter = iter::StepBy<iter::Skip<<&'a mut Buffer as IntoIterator>::IntoIter>>;
+    type IntoIter = PartialIterMut<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.buf
-            .data
-            .iter_mut()
-            .skip(self.offset)
-            .step_by(self.stride)
+        let inner = match &self.positions {
+            Positions::Stride { offset, stride } => {
+                PartialIterMutInner::Stride(self.buf.data.iter_mut().skip(*offset).step_by(*stride))
+            }
+            Positions::Indices(indices) => PartialIterMutInner::Indices(
+                self.buf.data.as_mut_ptr(),
+                indices.iter(),
+                PhantomData,
+            ),
+        };
+
+        PartialIterMut { inner }
     }
 }
 
 impl Buffer {
+    // A buffer of English letters
     pub fn new(data: Vec<Char>, original: Arc<String>) -> Self {
-        Self { data, original }
+        Self::with_alphabet(data, original, Alphabet::english())
+    }
+
+    pub fn with_alphabet(data: Vec<Char>, original: Arc<String>, alphabet: Arc<Alphabet>) -> Self {
+        debug_assert!(data.iter().all(|c| c.size() == alphabet.size()));
+
+        Self {
+            data,
+            original,
+            alphabet,
+        }
+    }
+
+    // Reads the symbols of the alphabet from the text, passing over anything else that is not a
+    // letter. Letters outside the alphabet are dealt with by the alphabet's `Policy`.
+    pub fn parse(data: &str, alphabet: Arc<Alphabet>) -> Result<Self, SimpleError> {
+        let original = Arc::new(data.to_owned());
+        let mut out = Vec::new();
+
+        for c in data.chars() {
+            if let Reading::Symbols(symbols) = alphabet.read(c)? {
+                out.extend(symbols);
+            }
+        }
+
+        Ok(Buffer::with_alphabet(out, original, alphabet))
     }
 
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn partial(self, offset: usize, stride: usize) -> PartialBuffer {
         assert!(stride > 0, "stride is zero");
 
         PartialBuffer {
             buf: self,
-            offset,
-            stride,
+            positions: Positions::Stride { offset, stride },
+        }
+    }
+
+    // A view of the given positions, in the order given, such as a column of a transposition or
+    // the route through a grid
+    pub fn mapped(self, indices: Vec<usize>) -> PartialBuffer {
+        let mut seen = vec![false; self.len()];
+
+        for &i in &indices {
+            assert!(i < self.len(), "index {} is out of bounds", i);
+            assert!(!seen[i], "index {} is used twice", i);
+            seen[i] = true;
+        }
+
+        PartialBuffer {
+            buf: self,
+            positions: Positions::Indices(indices.into()),
         }
     }
 
+    // A view of the positions where the mask is true
+    pub fn masked(self, mask: &[bool]) -> PartialBuffer {
+        assert_eq!(self.len(), mask.len(), "mask is the wrong length");
+
+        let indices = (0..mask.len()).filter(|&i| mask[i]).collect();
+        self.mapped(indices)
+    }
+
+    // Splits the letters into `period` columns, the first holding letters 0, period, 2 * period
+    // and so on. Each column is a buffer of its own, with no layout beyond its letters.
+    pub fn columns(&self, period: usize) -> Vec<Buffer> {
+        assert!(period > 0, "period is zero");
+
+        (0..period)
+            .map(|i| {
+                let data = self
+                    .data
+                    .iter()
+                    .skip(i)
+                    .step_by(period)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let original = data.iter().map(|&c| self.alphabet.symbol(c)).collect();
+                Buffer::with_alphabet(data, Arc::new(original), self.alphabet.clone())
+            })
+            .collect()
+    }
+
+    // Puts columns split from this buffer back together, in the layout of this buffer's text
+    pub fn interleave(&self, columns: &[Buffer]) -> Result<Buffer, SimpleError> {
+        let period = columns.len();
+
+        if period == 0 {
+            return Err(SimpleError::new("no columns to interleave"));
+        }
+
+        for (i, column) in columns.iter().enumerate() {
+            if column.len() != (self.len() + period - i - 1) / period {
+                return Err(SimpleError::new(format!(
+                    "column {} is the wrong length for a period of {}",
+                    i, period
+                )));
+            }
+
+            if column.alphabet != self.alphabet {
+                return Err(SimpleError::new(format!(
+                    "column {} is in a different alphabet",
+                    i
+                )));
+            }
+        }
+
+        let data = (0..self.len())
+            .map(|i| columns[i % period].data[i / period])
+            .collect();
+
+        Ok(Buffer::with_alphabet(
+            data,
+            self.original.clone(),
+            self.alphabet.clone(),
+        ))
+    }
+
+    // The text as it reads now, read again in another alphabet
+    pub fn to_alphabet(&self, alphabet: Arc<Alphabet>) -> Result<Buffer, SimpleError> {
+        Buffer::parse(&self.collect_original(), alphabet)
+    }
+
     pub fn score(&self, method: score::ScoreMethod) -> score::Score {
-        score::score(&self, method)
+        score::score(self, method)
     }
 
     pub fn original(&self) -> &str {
@@ -141,9 +382,11 @@ impl Buffer {
     }
 }
 
+// Buffers are equal when they hold the same letters, whatever the layout of their text. See
+// `compare` for how near two buffers are.
 impl std::cmp::PartialEq for Buffer {
     fn eq(&self, other: &Buffer) -> bool {
-        self.iter().zip(other.iter()).all(|(c1, c2)| c1 == c2) // Is this efficient?
+        self.data == other.data
     }
 }
 
@@ -151,21 +394,7 @@ impl FromStr for Buffer {
     type Err = SimpleError;
 
     fn from_str(data: &str) -> Result<Self, Self::Err> {
-        let original = Arc::new(data.to_owned());
-
-        let bytes: Result<Vec<Char>, _> = original
-            .chars()
-            .filter(|c| c.is_alphabetic())
-            .map(|c| {
-                if !c.is_ascii() {
-                    Err(SimpleError::new("string contains non-ascii characters"))
-                } else {
-                    Ok(Char::from(c))
-                }
-            })
-            .collect();
-
-        bytes.map(|b| Buffer::new(b, original))
+        Buffer::parse(data, Alphabet::english())
     }
 }
 
@@ -185,8 +414,10 @@ impl From<Buffer> for PartialBuffer {
     fn from(buf: Buffer) -> PartialBuffer {
         PartialBuffer {
             buf,
-            offset: 0,
-            stride: 1,
+            positions: Positions::Stride {
+                offset: 0,
+                stride: 1,
+            },
         }
     }
 }
@@ -199,18 +430,29 @@ impl From<PartialBuffer> for Buffer {
 
 impl CharStream<'_> for PartialBuffer {
     fn original(&self) -> &str {
-        &*self.buf.original
+        &self.buf.original
+    }
+
+    fn alphabet(&self) -> &Alphabet {
+        &self.buf.alphabet
     }
 
     fn len(&self) -> usize {
-        // Take ceiling of integer division: (len - offset) / stride
-        (self.buf.len() + self.stride - self.offset - 1) / self.stride
+        match &self.positions {
+            // Take ceiling of integer division: (len - offset) / stride
+            Positions::Stride { offset, stride } => (self.buf.len() + stride - offset - 1) / stride,
+            Positions::Indices(indices) => indices.len(),
+        }
     }
 }
 
 impl CharStream<'_> for Buffer {
     fn original(&self) -> &str {
-        &*self.original
+        &self.original
+    }
+
+    fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
     }
 
     fn len(&self) -> usize {
@@ -218,6 +460,31 @@ impl CharStream<'_> for Buffer {
     }
 }
 
+// Reads "layout", "letters", or "groups" for the usual fives, ten to a line. Another group size
+// can be given after a colon, as in "groups:4".
+impl FromStr for Format {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+
+        match (parts.next(), parts.next()) {
+            (Some("layout"), None) => Ok(Format::Layout),
+            (Some("letters"), None) => Ok(Format::Letters),
+            (Some("groups"), size) => {
+                let size = match size.map(|s| s.parse()) {
+                    Some(Ok(size)) if size > 0 => size,
+                    Some(_) => return Err(SimpleError::new("invalid group size")),
+                    None => 5,
+                };
+
+                Ok(Format::Groups { size, per_line: 10 })
+            }
+            _ => Err(SimpleError::new(format!("unknown format '{}'", s))),
+        }
+    }
+}
+
 impl fmt::Display for PartialBuffer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(&self.collect_original(), f)
@@ -242,6 +509,8 @@ impl fmt::Debug for Buffer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::alphabet::{Merge, Policy};
+    use crate::ciphers::{Caesar, Encrypt, PartialEncrypt};
 
     #[test]
     fn test_display() {
@@ -250,6 +519,96 @@ mod tests {
         assert_eq!(s, buf.to_string());
     }
 
+    #[test]
+    fn test_eq() {
+        assert_eq!(
+            Buffer::from("Attack at dawn"),
+            Buffer::from("ATTACKATDAWN!")
+        );
+        assert_ne!(Buffer::from("Attack at dawn"), Buffer::from("Attack at"));
+        assert_ne!(Buffer::from("Attack"), Buffer::from("Attack at dawn"));
+    }
+
+    #[test]
+    fn test_format() {
+        let buf = Buffer::from("Attack at dawn, hold the ridge until relieved.");
+        assert_eq!(buf.to_string(), buf.format(Format::Layout));
+        assert_eq!(
+            "ATTACKATDAWNHOLDTHERIDGEUNTILRELIEVED",
+            buf.format(Format::Letters)
+        );
+
+        let groups = Format::Groups {
+            size: 5,
+            per_line: 3,
+        };
+        assert_eq!(
+            "ATTAC KATDA WNHOL\nDTHER IDGEU NTILR\nELIEV ED",
+            buf.format(groups)
+        );
+
+        assert_eq!(Ok(Format::Letters), "letters".parse());
+        assert_eq!(
+            Ok(Format::Groups {
+                size: 4,
+                per_line: 10
+            }),
+            "groups:4".parse()
+        );
+        assert!("groups:0".parse::<Format>().is_err());
+        assert!("fives".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn test_mapped() {
+        let mut buffer = Buffer::from("ABCDEFGHIJ").mapped(vec![9, 0, 5]);
+
+        let letters = buffer.iter().map(|&c| char::from(c)).collect::<String>();
+        assert_eq!("JAF", letters);
+        assert_eq!(3, buffer.len());
+
+        for c in &mut buffer {
+            *c += 1;
+        }
+        assert_eq!("BBCDEGGHIK", Buffer::from(buffer).to_string());
+
+        let mask = [
+            true, false, true, false, false, false, false, false, false, true,
+        ];
+        let buffer = Buffer::from("ABCDEFGHIJ").masked(&mask);
+        let mut caesar = Caesar::new(3);
+        let buffer = caesar.encrypt_partial(buffer).unwrap();
+        assert_eq!("DBFDEFGHIM", Buffer::from(buffer).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "index 2 is used twice")]
+    fn test_mapped_twice() {
+        Buffer::from("ABCDEFGHIJ").mapped(vec![2, 4, 2]);
+    }
+
+    #[test]
+    fn test_columns() {
+        let buffer = Buffer::from("Attack at dawn, go");
+        let columns = buffer.columns(3);
+        let letters = columns.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["AAAAG", "TCTWO", "TKDN"], letters);
+
+        let mut columns = columns
+            .into_iter()
+            .enumerate()
+            .map(|(i, column)| Caesar::new(i as u8 + 1).encrypt(column).unwrap())
+            .collect::<Vec<_>>();
+        let out = buffer.interleave(&columns).unwrap();
+        assert_eq!("Bvwben bv gbyq, hq", out.to_string());
+        assert_eq!(out, out.interleave(&out.columns(3)).unwrap());
+
+        assert!(buffer.interleave(&columns[..2]).is_err());
+        columns.swap(1, 2);
+        assert!(buffer.interleave(&columns).is_err());
+        assert!(buffer.interleave(&[]).is_err());
+    }
+
     #[test]
     fn test_offset_stride() {
         let buffer = Buffer::from("ABCDEFGHIJ").partial(3, 2);
@@ -262,4 +621,79 @@ mod tests {
         assert_eq!("DFHJ", expected);
         assert_eq!(4, buffer.len());
     }
+
+    #[test]
+    fn test_alphabet() {
+        let alphabet = Arc::new(Alphabet::alphanumeric());
+        let mut buf = Buffer::parse("Meet at 0900, hut 7", alphabet).unwrap();
+        assert_eq!(14, buf.len());
+
+        for c in &mut buf {
+            *c += 10;
+        }
+        assert_eq!("Woo3 k3 AJAA, r43 H", buf.to_string());
+
+        assert!(Buffer::parse("Café", Alphabet::english()).is_err());
+    }
+
+    #[test]
+    fn test_twenty_five() {
+        let buf = Buffer::from("Jolly jackdaws, quietly");
+        let mut merged = buf
+            .to_alphabet(Arc::new(Alphabet::twenty_five(Merge::IJ)))
+            .unwrap();
+        assert_eq!(buf.len(), merged.len());
+        assert_eq!(buf.to_string(), merged.to_string());
+
+        for c in &mut merged {
+            *c += 1;
+        }
+        assert_eq!("Kpmmz kbdlebxt, rvkfumz", merged.to_string());
+
+        let buf = merged.to_alphabet(Alphabet::english()).unwrap();
+        assert_eq!("Kpmmz kbdlebxt, rvkfumz", buf.to_string());
+        assert_eq!(Char::MAX, buf.alphabet().size());
+
+        let vw = Arc::new(Alphabet::twenty_five(Merge::VW));
+        let buf = Buffer::parse("Wolves wander", vw).unwrap();
+        assert_eq!(12, buf.len());
+        assert_eq!("Wolves wander", buf.to_string());
+
+        let omit_q = Arc::new(Alphabet::twenty_five(Merge::OmitQ));
+        let mut omitted = Buffer::parse("Quiet, quite quick", omit_q).unwrap();
+        assert_eq!(12, omitted.len());
+        assert_eq!("Quiet, quite quick", omitted.to_string());
+
+        for c in &mut omitted {
+            *c += 1;
+        }
+        assert_eq!("Qvjfu, qvjuf qvjdl", omitted.to_string());
+    }
+
+    #[test]
+    fn test_policies() {
+        let text = "Straße à Genève";
+        let english = (*Alphabet::english()).clone();
+        assert!(Buffer::parse(text, Arc::new(english.clone())).is_err());
+
+        let strip = Arc::new(english.clone().with_policy(Policy::Strip));
+        let buf = Buffer::parse(text, strip).unwrap();
+        assert_eq!(10, buf.len());
+        assert_eq!("Strae  Genve", buf.to_string());
+
+        let keep = Arc::new(english.clone().with_policy(Policy::Keep));
+        let buf = Buffer::parse(text, keep).unwrap();
+        assert_eq!(10, buf.len());
+        assert_eq!(text, buf.to_string());
+
+        let transliterate = Arc::new(english.with_policy(Policy::Transliterate));
+        let mut buf = Buffer::parse(text, transliterate).unwrap();
+        assert_eq!(14, buf.len());
+        assert_eq!(text, buf.to_string());
+
+        for c in &mut buf {
+            *c += 1;
+        }
+        assert_eq!("Tusbttf b Hfofwf", buf.to_string());
+    }
 }
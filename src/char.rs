@@ -3,17 +3,31 @@ use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Char {
     c: u8,
+    // The size of the alphabet, which arithmetic wraps round at
+    size: u8,
 }
 
 impl Char {
+    // The size of the English alphabet, which `From<u8>` and `From<char>` work in
     pub const MAX: u8 = 26;
 
+    pub fn new(c: u8, size: u8) -> Char {
+        debug_assert!(c < size);
+        Char { c, size }
+    }
+
+    pub fn size(self) -> u8 {
+        self.size
+    }
+
     pub fn to_upper(self) -> char {
-        (self.c as u8 + b'A') as char
+        debug_assert!(self.c < Char::MAX);
+        (self.c + b'A') as char
     }
 
     pub fn to_lower(self) -> char {
-        (self.c as u8 + b'a') as char
+        debug_assert!(self.c < Char::MAX);
+        (self.c + b'a') as char
     }
 }
 
@@ -21,9 +35,8 @@ impl Add<u8> for Char {
     type Output = Char;
 
     fn add(self, other: u8) -> Char {
-        Char {
-            c: (self.c + other) % Char::MAX,
-        }
+        let c = (u16::from(self.c) + u16::from(other)) % u16::from(self.size);
+        Char::new(c as u8, self.size)
     }
 }
 
@@ -38,9 +51,9 @@ impl Sub<u8> for Char {
 
     fn sub(self, other: u8) -> Char {
         let a = self.c as i32 - other as i32;
-        let b = a + (Char::MAX as i32 * std::u8::MAX as i32);
-        let c = b % (Char::MAX as i32);
-        Char { c: c as u8 }
+        let b = a + (self.size as i32 * u8::MAX as i32);
+        let c = b % (self.size as i32);
+        Char::new(c as u8, self.size)
     }
 }
 
@@ -54,9 +67,8 @@ impl Mul<u8> for Char {
     type Output = Char;
 
     fn mul(self, other: u8) -> Char {
-        Char {
-            c: (self.c * other) % Char::MAX,
-        }
+        let c = (u16::from(self.c) * u16::from(other)) % u16::from(self.size);
+        Char::new(c as u8, self.size)
     }
 }
 
@@ -68,8 +80,7 @@ impl MulAssign<u8> for Char {
 
 impl From<u8> for Char {
     fn from(c: u8) -> Char {
-        debug_assert!(c < Char::MAX);
-        Char { c }
+        Char::new(c, Char::MAX)
     }
 }
 
@@ -77,9 +88,9 @@ impl From<char> for Char {
     fn from(c: char) -> Char {
         debug_assert!(c.is_ascii() && c.is_alphabetic());
         if c.is_uppercase() {
-            Char { c: c as u8 - b'A' }
+            Char::new(c as u8 - b'A', Char::MAX)
         } else {
-            Char { c: c as u8 - b'a' }
+            Char::new(c as u8 - b'a', Char::MAX)
         }
     }
 }
@@ -102,7 +113,7 @@ mod tests {
 
     #[test]
     fn test_upper_lower() {
-        let c = Char { c: 0 };
+        let c = Char::from(0);
 
         assert_eq!(c.to_upper(), 'A');
         assert_eq!(c.to_lower(), 'a');
@@ -110,8 +121,8 @@ mod tests {
 
     #[test]
     fn test_conversions() {
-        let a = Char { c: 0 };
-        let k = Char { c: 10 };
+        let a = Char::from(0);
+        let k = Char::from(10);
 
         assert_eq!(0, u8::from(a));
         assert_eq!(10, u8::from(k));
@@ -127,7 +138,7 @@ mod tests {
 
     #[test]
     fn test_ops() {
-        let a = Char { c: 0 };
+        let a = Char::from(0);
 
         assert_eq!(a, a + 0);
         assert_eq!(a, a - 0);
@@ -139,6 +150,17 @@ mod tests {
         assert_eq!(Char::from(10), a + Char::MAX + 10);
         assert_eq!(Char::from(16), a - Char::MAX - 10);
         assert_eq!(a, a * 123);
-        assert_eq!(Char { c: 4 }, Char { c: 1 } * 30);
+        assert_eq!(Char::from(4), Char::from(1) * 30);
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        let c = Char::new(30, 36);
+
+        assert_eq!(36, c.size());
+        assert_eq!(Char::new(4, 36), c + 10);
+        assert_eq!(Char::new(26, 36), c - 40);
+        assert_eq!(Char::new(24, 36), c * 200);
+        assert_eq!(Char::new(0, 25), Char::new(24, 25) + 1);
     }
 }
@@ -1,25 +1,33 @@
-use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
-use crate::{Buffer, Char, PartialBuffer};
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt, Resumable};
+use crate::{Buffer, CharStream, PartialBuffer};
 use simple_error::SimpleError;
 
+// Works modulo the size of the buffer's alphabet, so `a` has to be coprime to that size
 pub struct Affine {
     a: u8,
     b: u8,
-    mmi_a: u8,
 }
 
 impl Affine {
     pub fn new(a: u8, b: u8) -> Self {
-        let mmi_a = (0..Char::MAX)
-            .find(|&i| (a * i) % Char::MAX == 1)
-            .expect("unable to find modular multiplicative inverse of a");
+        Self { a, b }
+    }
+
+    // The modular multiplicative inverse of `a`
+    fn inverse(&self, buf: &PartialBuffer) -> Result<u8, SimpleError> {
+        let size = u16::from(buf.alphabet().size());
 
-        Self { a, b, mmi_a }
+        (1..size)
+            .find(|&i| (u16::from(self.a) * i) % size == 1)
+            .map(|i| i as u8)
+            .ok_or_else(|| SimpleError::new("a must be coprime to the size of the alphabet"))
     }
 }
 
 impl PartialEncrypt for Affine {
     fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        self.inverse(&buf)?;
+
         for x in &mut buf {
             *x = (*x * self.a) + self.b;
         }
@@ -30,8 +38,10 @@ impl PartialEncrypt for Affine {
 
 impl PartialDecrypt for Affine {
     fn decrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        let mmi_a = self.inverse(&buf)?;
+
         for x in &mut buf {
-            *x = (*x - self.b) * self.mmi_a;
+            *x = (*x - self.b) * mmi_a;
         }
 
         Ok(buf)
@@ -40,9 +50,15 @@ impl PartialDecrypt for Affine {
 
 derive_encrypt_decrypt!(Affine, SimpleError);
 
+impl Resumable for Affine {
+    fn skip(&mut self, _: usize) {}
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Alphabet;
+    use std::sync::Arc;
 
     #[test]
     fn test_encrypt_decrypt() {
@@ -55,4 +71,21 @@ mod tests {
         let buf = affine.decrypt(buf).unwrap();
         assert_eq!("Hello world!", buf.to_string());
     }
+
+    #[test]
+    fn test_alphabet() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Hello 2 world!", alphanumeric).unwrap();
+
+        // 3 shares a factor with 36
+        assert!(Affine::new(3, 5).encrypt(buf.clone()).is_err());
+
+        let mut affine = Affine::new(5, 7);
+        let ciphertext = affine.encrypt(buf.clone()).unwrap();
+        assert_ne!(buf.to_string(), ciphertext.to_string());
+        assert_eq!(
+            buf.to_string(),
+            affine.decrypt(ciphertext).unwrap().to_string()
+        );
+    }
 }
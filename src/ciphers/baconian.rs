@@ -0,0 +1,285 @@
+use super::{Decrypt, Encrypt};
+use crate::{Buffer, Char};
+use simple_error::SimpleError;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BaconianVariant {
+    // The original alphabet, where I/J and U/V share a code
+    TwentyFour,
+    TwentySix,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Class {
+    A,
+    B,
+}
+
+// How the two classes are distinguished in a carrier text
+#[derive(Debug, Clone, PartialEq)]
+pub enum CarrierRule {
+    // Lowercase letters are A, uppercase letters are B
+    Case,
+    // Letters A-M are A, letters N-Z are B
+    Halves,
+    // Explicit classes for individual characters, e.g. two fonts or marked letters.
+    // Characters not in the mapping are skipped.
+    Mapping(HashMap<char, Class>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Baconian {
+    variant: BaconianVariant,
+    rule: CarrierRule,
+}
+
+impl Baconian {
+    pub fn new(variant: BaconianVariant) -> Self {
+        Self {
+            variant,
+            rule: CarrierRule::Case,
+        }
+    }
+
+    pub fn with_rule(mut self, rule: CarrierRule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    fn code(&self, c: Char) -> u8 {
+        let c = u8::from(c);
+
+        match self.variant {
+            BaconianVariant::TwentySix => c,
+            BaconianVariant::TwentyFour => match c {
+                0..=8 => c,      // A to I
+                9..=20 => c - 1, // J to U
+                _ => c - 2,      // V to Z
+            },
+        }
+    }
+
+    fn letter(&self, code: u8) -> Option<Char> {
+        match self.variant {
+            BaconianVariant::TwentySix if code < 26 => Some(Char::from(code)),
+            BaconianVariant::TwentyFour if code < 24 => Some(Char::from(match code {
+                0..=8 => code,
+                9..=19 => code + 1,
+                _ => code + 2,
+            })),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, buf: &Buffer) -> Result<Vec<Class>, SimpleError> {
+        check_alphabet!(buf, SimpleError);
+
+        let mut out = Vec::with_capacity(buf.len() * 5);
+
+        for &c in buf {
+            let code = self.code(c);
+
+            for bit in (0..5).rev() {
+                out.push(if code & (1 << bit) == 0 {
+                    Class::A
+                } else {
+                    Class::B
+                });
+            }
+        }
+
+        Ok(out)
+    }
+
+    // Trailing classes that do not make up a full group are ignored
+    pub fn decode(&self, classes: &[Class]) -> Result<Buffer, SimpleError> {
+        let data = classes
+            .chunks_exact(5)
+            .map(|group| {
+                let code = group
+                    .iter()
+                    .fold(0, |acc, &cl| (acc << 1) | (cl == Class::B) as u8);
+
+                self.letter(code)
+                    .ok_or_else(|| SimpleError::new("invalid baconian group"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let original = data.iter().map(|&c| char::from(c)).collect::<String>();
+        Ok(Buffer::new(data, Arc::new(original)))
+    }
+
+    fn classify(&self, c: char) -> Option<Class> {
+        match &self.rule {
+            CarrierRule::Case if c.is_ascii_alphabetic() => Some(if c.is_ascii_uppercase() {
+                Class::B
+            } else {
+                Class::A
+            }),
+            CarrierRule::Halves if c.is_ascii_alphabetic() => {
+                Some(if c.to_ascii_uppercase() <= 'M' {
+                    Class::A
+                } else {
+                    Class::B
+                })
+            }
+            CarrierRule::Mapping(map) => map.get(&c).cloned(),
+            _ => None,
+        }
+    }
+
+    // Reads the hidden message out of the carrier's original text
+    pub fn extract(&self, carrier: &Buffer) -> Result<Buffer, SimpleError> {
+        let classes = carrier
+            .original()
+            .chars()
+            .filter_map(|c| self.classify(c))
+            .collect::<Vec<_>>();
+
+        self.decode(&classes)
+    }
+
+    // Hides a message in the carrier by changing the case of its letters. Only the case
+    // rule can be applied to an existing text; the others constrain which letters are used.
+    pub fn hide(&self, buf: &Buffer, carrier: &Buffer) -> Result<Buffer, SimpleError> {
+        if self.rule != CarrierRule::Case {
+            return Err(SimpleError::new(
+                "only the case rule can hide a message in an existing carrier",
+            ));
+        }
+
+        let classes = self.encode(buf)?;
+        if classes.len() > carrier.len() {
+            return Err(SimpleError::new("carrier text is too short"));
+        }
+
+        let mut classes = classes.into_iter();
+        let original = carrier
+            .original()
+            .chars()
+            .map(|c| {
+                if !c.is_ascii_alphabetic() {
+                    return c;
+                }
+
+                match classes.next() {
+                    Some(Class::A) => c.to_ascii_lowercase(),
+                    Some(Class::B) => c.to_ascii_uppercase(),
+                    None => c.to_ascii_lowercase(),
+                }
+            })
+            .collect::<String>();
+
+        Ok(Buffer::from(&original))
+    }
+}
+
+impl Encrypt for Baconian {
+    type Error = SimpleError;
+
+    // Produces the groups as the letters A and B
+    fn encrypt(&mut self, buf: Buffer) -> Result<Buffer, Self::Error> {
+        let classes = self.encode(&buf)?;
+        let data = classes
+            .iter()
+            .map(|&cl| Char::from(if cl == Class::A { 'A' } else { 'B' }))
+            .collect::<Vec<_>>();
+
+        let original = data
+            .chunks(5)
+            .map(|group| group.iter().map(|&c| char::from(c)).collect::<String>())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(Buffer::new(data, Arc::new(original)))
+    }
+}
+
+impl Decrypt for Baconian {
+    type Error = SimpleError;
+
+    fn decrypt(&mut self, buf: Buffer) -> Result<Buffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        let classes = buf
+            .into_iter()
+            .map(|&c| match char::from(c) {
+                'A' => Ok(Class::A),
+                'B' => Ok(Class::B),
+                _ => Err(SimpleError::new(
+                    "baconian ciphertext may only contain A and B",
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.decode(&classes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alphabet;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut bacon = Baconian::new(BaconianVariant::TwentySix);
+        let buf = bacon.encrypt(Buffer::from("Hello")).unwrap();
+        assert_eq!("AABBB AABAA ABABB ABABB ABBBA", buf.to_string());
+
+        let buf = bacon.decrypt(buf).unwrap();
+        assert_eq!("HELLO", buf.to_string());
+
+        let mut bacon = Baconian::new(BaconianVariant::TwentyFour);
+        let buf = bacon.encrypt(Buffer::from("Jove")).unwrap();
+        assert_eq!("ABAAA ABBAB BAABB AABAA", buf.to_string());
+
+        let buf = bacon.decrypt(buf).unwrap();
+        assert_eq!("IOUE", buf.to_string());
+
+        assert!(bacon.decrypt(Buffer::from("BBBBB")).is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let mut bacon = Baconian::new(BaconianVariant::TwentySix);
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("AABBB AABAA 1", alphanumeric).unwrap();
+
+        assert!(bacon.encrypt(buf.clone()).is_err());
+        assert!(bacon.decrypt(buf).is_err());
+    }
+
+    #[test]
+    fn test_hide_extract() {
+        let bacon = Baconian::new(BaconianVariant::TwentySix);
+        let carrier = Buffer::from("The quick brown fox jumps over the lazy dog, again and again.");
+
+        let hidden = bacon.hide(&Buffer::from("Flee"), &carrier).unwrap();
+        assert_eq!(
+            "thE qUiCk BRowN fox jUmps over the lazy dog, again and again.",
+            hidden.to_string()
+        );
+
+        // The rest of the carrier is lowercase, so it decodes as padding A's
+        assert_eq!("FLEEAAAAA", bacon.extract(&hidden).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_rules() {
+        let bacon = Baconian::new(BaconianVariant::TwentySix).with_rule(CarrierRule::Halves);
+        let carrier = Buffer::from("Abcde Fghij Abcdn");
+        assert_eq!("AAB", bacon.extract(&carrier).unwrap().to_string());
+        assert!(bacon.hide(&Buffer::from("A"), &carrier).is_err());
+
+        let mut marks = HashMap::new();
+        marks.insert('.', Class::A);
+        marks.insert('*', Class::B);
+        let bacon =
+            Baconian::new(BaconianVariant::TwentySix).with_rule(CarrierRule::Mapping(marks));
+        let carrier = Buffer::from("Wo.rd.s* wi.th* ma.rk*s o.n* th*em");
+        assert_eq!("FL", bacon.extract(&carrier).unwrap().to_string());
+    }
+}
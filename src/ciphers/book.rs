@@ -0,0 +1,252 @@
+use crate::{Buffer, Char};
+use simple_error::SimpleError;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const LEN: usize = Char::MAX as usize;
+
+// Where a word is found in the book. Either way of counting starts from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reference {
+    // The word's position in the whole text, as in the Beale ciphers
+    Word(usize),
+    // Page, line on the page and word on the line, written as 3.12.4
+    Location {
+        page: usize,
+        line: usize,
+        word: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceStyle {
+    Word,
+    Location,
+}
+
+// A book cipher, where each letter of the plaintext is given as a reference to a word of the
+// book starting with that letter. Pages of the book are split by form feeds and lines by
+// newlines. Words without a letter are still counted but never used.
+#[derive(Debug, Clone)]
+pub struct Book {
+    words: Vec<Word>,
+}
+
+#[derive(Debug, Clone)]
+struct Word {
+    letter: Option<Char>,
+    page: usize,
+    line: usize,
+    word: usize,
+}
+
+impl Book {
+    pub fn new(text: &str) -> Result<Self, SimpleError> {
+        let mut words = Vec::new();
+
+        for (page, page_text) in text.split('\x0c').enumerate() {
+            for (line, line_text) in page_text.lines().enumerate() {
+                for (word, word_text) in line_text.split_whitespace().enumerate() {
+                    words.push(Word {
+                        letter: word_text
+                            .chars()
+                            .find(|c| c.is_ascii_alphabetic())
+                            .map(Char::from),
+                        page: page + 1,
+                        line: line + 1,
+                        word: word + 1,
+                    });
+                }
+            }
+        }
+
+        if words.iter().all(|w| w.letter.is_none()) {
+            return Err(SimpleError::new("book has no words"));
+        }
+
+        Ok(Self { words })
+    }
+
+    fn reference(&self, i: usize, style: ReferenceStyle) -> Reference {
+        let w = &self.words[i];
+
+        match style {
+            ReferenceStyle::Word => Reference::Word(i + 1),
+            ReferenceStyle::Location => Reference::Location {
+                page: w.page,
+                line: w.line,
+                word: w.word,
+            },
+        }
+    }
+
+    // Each letter uses the words starting with it in turn, so repeated letters are not given
+    // away by repeated references
+    pub fn encrypt(
+        &self,
+        buf: &Buffer,
+        style: ReferenceStyle,
+    ) -> Result<Vec<Reference>, SimpleError> {
+        check_alphabet!(buf, SimpleError);
+
+        let mut table = vec![Vec::new(); LEN];
+        for (i, w) in self.words.iter().enumerate() {
+            if let Some(c) = w.letter {
+                table[usize::from(u8::from(c))].push(i);
+            }
+        }
+
+        let mut used = [0; LEN];
+        let mut out = Vec::with_capacity(buf.len());
+
+        for &c in buf {
+            let i = usize::from(u8::from(c));
+
+            if table[i].is_empty() {
+                return Err(SimpleError::new(format!(
+                    "no word in the book starts with {}",
+                    char::from(c)
+                )));
+            }
+
+            out.push(self.reference(table[i][used[i] % table[i].len()], style));
+            used[i] += 1;
+        }
+
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, refs: &[Reference]) -> Result<Buffer, SimpleError> {
+        let data = refs
+            .iter()
+            .map(|&r| {
+                let word = match r {
+                    Reference::Word(n) => n.checked_sub(1).and_then(|i| self.words.get(i)),
+                    Reference::Location { page, line, word } => self
+                        .words
+                        .iter()
+                        .find(|w| w.page == page && w.line == line && w.word == word),
+                };
+
+                word.and_then(|w| w.letter)
+                    .ok_or_else(|| SimpleError::new(format!("no letter at {}", r)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let original = data.iter().map(|&c| char::from(c)).collect::<String>();
+        Ok(Buffer::new(data, Arc::new(original)))
+    }
+
+    // Decodes a list of references split by spaces or commas
+    pub fn decode(&self, refs: &str) -> Result<Buffer, SimpleError> {
+        self.decrypt(&parse_references(refs)?)
+    }
+}
+
+pub fn parse_references(refs: &str) -> Result<Vec<Reference>, SimpleError> {
+    refs.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse())
+        .collect()
+}
+
+pub fn format_references(refs: &[Reference]) -> String {
+    refs.iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reference::Word(n) => write!(f, "{}", n),
+            Reference::Location { page, line, word } => write!(f, "{}.{}.{}", page, line, word),
+        }
+    }
+}
+
+impl FromStr for Reference {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split('.')
+            .map(|p| p.parse::<usize>().ok().filter(|&n| n > 0))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| SimpleError::new(format!("invalid reference '{}'", s)))?;
+
+        match parts[..] {
+            [n] => Ok(Reference::Word(n)),
+            [page, line, word] => Ok(Reference::Location { page, line, word }),
+            _ => Err(SimpleError::new(format!("invalid reference '{}'", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alphabet;
+
+    const BOOK: &str = "When in the course of human events it becomes necessary\n\
+                        for one people to dissolve the political bands which have\n\
+                        connected them with another\x0cand to assume among the powers of\n\
+                        the earth the separate and equal station to which the laws\n\
+                        of nature and of nature's God entitle them";
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let book = Book::new(BOOK).unwrap();
+        let buf = Buffer::from("Tea at the station");
+
+        for &style in &[ReferenceStyle::Word, ReferenceStyle::Location] {
+            let refs = book.encrypt(&buf, style).unwrap();
+            assert_eq!(buf.len(), refs.len());
+
+            // The two As in a row come from different words
+            assert_ne!(refs[2], refs[3]);
+
+            let text = format_references(&refs);
+            assert_eq!("TEAATTHESTATION", book.decode(&text).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn test_references() {
+        let book = Book::new(BOOK).unwrap();
+        let refs = book
+            .encrypt(&Buffer::from("gap"), ReferenceStyle::Location)
+            .unwrap();
+        assert_eq!("2.3.6 1.3.4 1.2.3", format_references(&refs));
+
+        let refs = book
+            .encrypt(&Buffer::from("gap"), ReferenceStyle::Word)
+            .unwrap();
+        assert_eq!("48 24 13", format_references(&refs));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let book = Book::new(BOOK).unwrap();
+
+        assert!(book
+            .encrypt(&Buffer::from("xyz"), ReferenceStyle::Word)
+            .is_err());
+        assert!(book.decode("1000").is_err());
+        assert!(book.decode("0").is_err());
+        assert!(book.decode("1.2").is_err());
+        assert!(book.decode("9.1.1").is_err());
+        assert!(Book::new("1 2 3").is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let book = Book::new(BOOK).unwrap();
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Tea at 4", alphanumeric).unwrap();
+
+        assert!(book.encrypt(&buf, ReferenceStyle::Word).is_err());
+    }
+}
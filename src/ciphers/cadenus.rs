@@ -0,0 +1,252 @@
+use super::{Decrypt, Encrypt};
+use crate::meta::HeuristicTarget;
+use crate::{Buffer, Char};
+use rand::Rng;
+use simple_error::SimpleError;
+use smallvec::SmallVec;
+use std::fmt;
+
+// Every column of the block holds this many letters
+pub const ROWS: usize = 25;
+
+// The letters down the side of the block, with V and W sharing a row. The row of each keyword
+// letter is brought to the top of its column.
+const SIDE: &[u8; ROWS] = b"AZYXVUTSRQPONMLKJIHGFEDCB";
+
+// The ACA Cadenus. The plaintext is written in rows under the keyword, 25 rows deep, and the
+// columns put in alphabetical order of their keyword letters. Each column is then rotated up to
+// start at the row of its keyword letter, and the block read off by rows. Messages must be
+// exactly 25 letters for each letter of the keyword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cadenus {
+    keyword: SmallVec<[u8; 16]>,
+}
+
+fn side_row(c: u8) -> usize {
+    let c = if c == b'W' { b'V' } else { c };
+    SIDE.iter().position(|&s| s == c).unwrap()
+}
+
+impl Cadenus {
+    pub fn new(keyword: &str) -> Result<Self, SimpleError> {
+        let keyword = keyword
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_uppercase() as u8)
+            .collect::<SmallVec<[u8; 16]>>();
+
+        if keyword.is_empty() {
+            return Err(SimpleError::new("keyword must contain at least one letter"));
+        }
+
+        Ok(Self { keyword })
+    }
+
+    pub fn keyword(&self) -> String {
+        self.keyword.iter().map(|&c| char::from(c)).collect()
+    }
+
+    // The plaintext index of the letter at each index of the ciphertext
+    fn mapping(&self, len: usize) -> Result<Vec<usize>, SimpleError> {
+        let width = self.keyword.len();
+        if len != width * ROWS {
+            return Err(SimpleError::new(format!(
+                "text must be {} letters long for a keyword of {} letters",
+                width * ROWS,
+                width
+            )));
+        }
+
+        // Equal keyword letters keep their columns in order
+        let mut order = (0..width).collect::<Vec<_>>();
+        order.sort_by_key(|&j| self.keyword[j]);
+
+        let mut out = Vec::with_capacity(len);
+        for row in 0..ROWS {
+            for &j in &order {
+                let shift = side_row(self.keyword[j]);
+                out.push(((row + shift) % ROWS) * width + j);
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl Encrypt for Cadenus {
+    type Error = SimpleError;
+
+    fn encrypt(&mut self, mut buf: Buffer) -> Result<Buffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+        let letters = (&buf).into_iter().cloned().collect::<Vec<Char>>();
+        let mapping = self.mapping(letters.len())?;
+
+        for (c, &i) in (&mut buf).into_iter().zip(mapping.iter()) {
+            *c = letters[i];
+        }
+
+        Ok(buf)
+    }
+}
+
+impl Decrypt for Cadenus {
+    type Error = SimpleError;
+
+    fn decrypt(&mut self, mut buf: Buffer) -> Result<Buffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+        let letters = (&buf).into_iter().cloned().collect::<Vec<Char>>();
+        let mapping = self.mapping(letters.len())?;
+
+        let mut plain = letters.clone();
+        for (&c, &i) in letters.iter().zip(mapping.iter()) {
+            plain[i] = c;
+        }
+
+        for (c, &p) in (&mut buf).into_iter().zip(plain.iter()) {
+            *c = p;
+        }
+
+        Ok(buf)
+    }
+}
+
+impl fmt::Display for Cadenus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.keyword())
+    }
+}
+
+fn rand_letter<R: Rng + ?Sized>(rng: &mut R) -> u8 {
+    b'A' + rng.gen_range(0, Char::MAX)
+}
+
+// The key param is the keyword length, which is the ciphertext length over 25. Tweaks change or
+// swap keyword letters, so move the rotation and position of a column at once.
+//
+// The plaintext can only be found up to a rotation, as the same ciphertext comes from the
+// message started a few letters later under another keyword. Those keys differ only in the
+// quadgrams that wrap round, so a search may return the message split part way through.
+impl HeuristicTarget for Cadenus {
+    type KeyParam = usize;
+
+    fn rand_key<R: Rng + ?Sized>(param: Self::KeyParam, rng: &mut R) -> Self {
+        assert!(param > 0, "keyword length was zero");
+
+        Self {
+            keyword: (0..param).map(|_| rand_letter(rng)).collect(),
+        }
+    }
+
+    fn tweak_key<R: Rng + ?Sized>(&self, _param: Self::KeyParam, rng: &mut R) -> Self {
+        let mut s = self.clone();
+        let i = rng.gen_range(0, s.keyword.len());
+
+        if rng.gen() {
+            s.keyword[i] = rand_letter(rng);
+        } else {
+            let j = rng.gen_range(0, s.keyword.len());
+            s.keyword.swap(i, j);
+        }
+
+        s
+    }
+
+    // Counts through every keyword of the given length, so only practical for short keywords
+    fn next_key(key: Option<Self>, param: Self::KeyParam) -> Option<Self> {
+        let mut key = match key {
+            Some(k) => k,
+            None => {
+                return Some(Self {
+                    keyword: SmallVec::from_elem(b'A', param),
+                })
+            }
+        };
+
+        for c in key.keyword.iter_mut().rev() {
+            if *c < b'Z' {
+                *c += 1;
+                return Some(key);
+            }
+
+            *c = b'A';
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::hillclimb::HillClimb;
+    use crate::meta::Metaheuristic;
+    use crate::score::ScoreMethod;
+    use crate::Alphabet;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        // The example from the ACA
+        let mut cadenus = Cadenus::new("EASY").unwrap();
+        let buf = Buffer::from(
+            "A severe limitation on the usefulness of the Cadenus is that every message must be a \
+             multiple of twenty-five letters long",
+        );
+
+        let buf = cadenus.encrypt(buf).unwrap();
+        assert_eq!(
+            "SYSTRETOMTATTLUSOATLEEESFIYHEASDFNMSCHBHNEUVSNPMTOFARENUSEIEEIELTARLMENTIEETOGEVESIT\
+             FAISLTNGEEUVOWUL",
+            buf.to_string()
+                .chars()
+                .filter(|c| c.is_ascii_alphabetic())
+                .collect::<String>()
+                .to_uppercase()
+        );
+
+        let buf = cadenus.decrypt(buf).unwrap();
+        assert_eq!(
+            "A severe limitation on the usefulness of the Cadenus is that every message must be a \
+             multiple of twenty-five letters long",
+            buf.to_string()
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Cadenus::new("").is_err());
+
+        let mut cadenus = Cadenus::new("EASY").unwrap();
+        assert!(cadenus.encrypt(Buffer::from("Too short")).is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse(&"ABCDE12345".repeat(10), alphanumeric).unwrap();
+
+        let mut cadenus = Cadenus::new("EASY").unwrap();
+        assert!(cadenus.encrypt(buf.clone()).is_err());
+        assert!(cadenus.decrypt(buf).is_err());
+    }
+
+    #[test]
+    fn test_hillclimb() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                                 TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYH"
+            .into();
+        let ciphertext = Cadenus::new("GARDEN")
+            .unwrap()
+            .encrypt(plaintext.clone())
+            .unwrap();
+
+        let results = HillClimb::new(1000, 30)
+            .crack_ciphertext::<Cadenus>(ciphertext, 6, ScoreMethod::Quadgrams, 1)
+            .unwrap();
+
+        // Any rotation of the plaintext is as good a solution
+        let doubled = plaintext.to_string().repeat(2);
+        assert!(doubled.contains(&results[0].buf.to_string()));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt, Resumable};
 use crate::meta::HeuristicTarget;
 use crate::{Buffer, Char, PartialBuffer};
 use rand::Rng;
@@ -37,6 +37,10 @@ impl PartialDecrypt for Caesar {
 
 derive_encrypt_decrypt!(Caesar, SimpleError);
 
+impl Resumable for Caesar {
+    fn skip(&mut self, _: usize) {}
+}
+
 impl PartialEq for Caesar {
     fn eq(&self, other: &Caesar) -> bool {
         self.shift == other.shift
@@ -75,6 +79,8 @@ impl HeuristicTarget for Caesar {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Alphabet;
+    use std::sync::Arc;
 
     #[test]
     fn test_encrypt_decrypt() {
@@ -88,6 +94,19 @@ mod tests {
         assert_eq!("Hello world!", buf.to_string());
     }
 
+    #[test]
+    fn test_alphabet() {
+        let mut caesar = Caesar::new(5);
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Hello world 1999!", alphanumeric).unwrap();
+
+        let buf = caesar.encrypt(buf).unwrap();
+        assert_eq!("Mjqqt 1twqi 6EEE!", buf.to_string());
+
+        let buf = caesar.decrypt(buf).unwrap();
+        assert_eq!("Hello world 1999!", buf.to_string());
+    }
+
     #[test]
     fn test_next_key() {
         let mut keys = 0;
@@ -0,0 +1,466 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use crate::{Buffer, Char, PartialBuffer};
+use simple_error::SimpleError;
+use std::cmp::Reverse;
+use std::fmt;
+
+const LEN: usize = Char::MAX as usize;
+const NADIR: usize = LEN / 2;
+
+// Number of starting pairs tried by the known plaintext search
+const MAX_ANCHORS: usize = 20;
+// Steps each starting pair gets in the first round of the search, and the number of rounds,
+// each giving four times as many as the last, before the search gives up
+const FIRST_ROUND_STEPS: usize = 10_000;
+const MAX_ROUNDS: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chaocipher {
+    // Alphabets at the start of each message. The left (cipher) wheel and right (plain) wheel
+    // are permuted after every letter, so the working copies are rebuilt on each call.
+    left: [Char; LEN],
+    right: [Char; LEN],
+}
+
+// Brings position `i` to the zenith, then moves the letter after the zenith to the nadir
+fn permute_left<T: Copy>(wheel: &mut [T; LEN], i: usize) {
+    wheel.rotate_left(i);
+
+    let extracted = wheel[1];
+    wheel.copy_within(2..=NADIR, 1);
+    wheel[NADIR] = extracted;
+}
+
+// Brings the letter after position `i` to the zenith, then moves the third letter to the nadir
+fn permute_right<T: Copy>(wheel: &mut [T; LEN], i: usize) {
+    wheel.rotate_left((i + 1) % LEN);
+
+    let extracted = wheel[2];
+    wheel.copy_within(3..=NADIR, 2);
+    wheel[NADIR] = extracted;
+}
+
+fn parse_alphabet(alphabet: &str) -> Result<[Char; LEN], SimpleError> {
+    let mut out = [Char::from(0); LEN];
+    let mut seen = [false; LEN];
+    let letters = alphabet.chars().filter(|c| c.is_ascii_alphabetic());
+
+    for (i, c) in letters.enumerate() {
+        let c = Char::from(c);
+        let idx = usize::from(u8::from(c));
+
+        if i >= LEN || seen[idx] {
+            return Err(SimpleError::new(
+                "alphabets must contain every letter exactly once",
+            ));
+        }
+
+        seen[idx] = true;
+        out[i] = c;
+    }
+
+    if seen.iter().any(|&s| !s) {
+        return Err(SimpleError::new(
+            "alphabets must contain every letter exactly once",
+        ));
+    }
+
+    Ok(out)
+}
+
+impl Chaocipher {
+    pub fn new(left: &str, right: &str) -> Result<Self, SimpleError> {
+        Ok(Self {
+            left: parse_alphabet(left)?,
+            right: parse_alphabet(right)?,
+        })
+    }
+
+    fn run(&self, mut buf: PartialBuffer, encrypt: bool) -> PartialBuffer {
+        let (mut left, mut right) = (self.left, self.right);
+
+        for c in &mut buf {
+            let (from, to) = if encrypt {
+                (&right, &left)
+            } else {
+                (&left, &right)
+            };
+
+            let i = from.iter().position(|x| x == c).unwrap();
+            *c = to[i];
+
+            permute_left(&mut left, i);
+            permute_right(&mut right, i);
+        }
+
+        buf
+    }
+
+    // Recovers the starting alphabets from a matching plaintext and ciphertext. Rotating both
+    // alphabets together gives the same cipher, so the search starts from one letter pair placed
+    // at the zenith and works outwards, following which positions each letter must have been in
+    // as the wheels are permuted. Letters the texts do not pin down are filled in alphabetically.
+    // The search is bounded, so on texts that are hard to place it can give up without a key.
+    pub fn from_known_plaintext(
+        plaintext: &Buffer,
+        ciphertext: &Buffer,
+    ) -> Result<Self, SimpleError> {
+        Self::search(plaintext, ciphertext, MAX_ROUNDS)
+    }
+
+    fn search(
+        plaintext: &Buffer,
+        ciphertext: &Buffer,
+        max_rounds: usize,
+    ) -> Result<Self, SimpleError> {
+        if plaintext.len() != ciphertext.len() {
+            return Err(SimpleError::new("plaintext and ciphertext lengths differ"));
+        }
+
+        if plaintext.is_empty() {
+            return Err(SimpleError::new("no known plaintext given"));
+        }
+
+        let pairs = plaintext
+            .into_iter()
+            .cloned()
+            .zip(ciphertext.into_iter().cloned())
+            .collect::<Vec<_>>();
+
+        let mut slots = [0; LEN];
+        for (i, s) in slots.iter_mut().enumerate() {
+            *s = i;
+        }
+
+        // Branching is worst while the wheels are empty, so the search starts from pairs whose
+        // letters come up most often. How long it takes varies a lot with the starting pair, so
+        // each gets a limited number of steps before moving on to the next, with the limit
+        // raised after every round, up to a limit.
+        let mut counts = ([0; LEN], [0; LEN]);
+        for &(p, c) in &pairs {
+            counts.0[usize::from(u8::from(p))] += 1;
+            counts.1[usize::from(u8::from(c))] += 1;
+        }
+
+        let mut anchors = (0..pairs.len()).collect::<Vec<_>>();
+        anchors.sort_by_key(|&n| {
+            let (p, c) = pairs[n];
+            Reverse(counts.0[usize::from(u8::from(p))] + counts.1[usize::from(u8::from(c))])
+        });
+        anchors.truncate(MAX_ANCHORS);
+
+        let mut search = Search {
+            pairs: &pairs,
+            left: [None; LEN],
+            right: [None; LEN],
+            steps: 0,
+            max_steps: 0,
+        };
+
+        let mut max_steps = FIRST_ROUND_STEPS;
+        let mut rounds = 0;
+        let front = 'outer: loop {
+            if rounds == max_rounds {
+                return Err(SimpleError::new("search gave up"));
+            }
+
+            for &anchor in &anchors {
+                let window = Window {
+                    start: anchor,
+                    end: anchor,
+                    front: (slots, slots),
+                    back: (slots, slots),
+                };
+
+                search.left = [None; LEN];
+                search.right = [None; LEN];
+                search.steps = 0;
+                search.max_steps = max_steps;
+
+                if let Some(front) = search.step(window) {
+                    break 'outer front;
+                }
+
+                // Every pair is in every key, so a search that ran to the end rules out all keys
+                if search.steps <= max_steps {
+                    return Err(SimpleError::new("no key produces this ciphertext"));
+                }
+            }
+
+            max_steps *= 4;
+            rounds += 1;
+        };
+
+        let arrange = |slots: &[usize; LEN], wheel: &[Option<Char>; LEN]| {
+            let mut out = [None; LEN];
+            for (o, &s) in out.iter_mut().zip(slots.iter()) {
+                *o = wheel[s];
+            }
+            fill(out)
+        };
+
+        Ok(Self {
+            left: arrange(&front.0, &search.left),
+            right: arrange(&front.1, &search.right),
+        })
+    }
+}
+
+// Undoes `permute_left`, leaving the letter that was looked up at the zenith
+fn unpermute_left<T: Copy>(wheel: &mut [T; LEN]) {
+    let extracted = wheel[NADIR];
+    wheel.copy_within(1..NADIR, 2);
+    wheel[1] = extracted;
+}
+
+// Undoes `permute_right`, leaving the letter that was looked up at the zenith
+fn unpermute_right<T: Copy>(wheel: &mut [T; LEN]) {
+    let extracted = wheel[NADIR];
+    wheel.copy_within(2..NADIR, 3);
+    wheel[2] = extracted;
+    wheel.rotate_right(1);
+}
+
+// The letters between `start` and `end` have been placed. The starting slot of each letter is
+// tracked through the permutations, which only depend on positions, with `front` holding the
+// slots at each position of the (left, right) wheels before `start` and `back` after `end`.
+#[derive(Clone, Copy)]
+struct Window {
+    start: usize,
+    end: usize,
+    front: ([usize; LEN], [usize; LEN]),
+    back: ([usize; LEN], [usize; LEN]),
+}
+
+// Depth first search over the starting slot of each letter, assigning letters to slots as
+// they're encountered. The window is grown in whichever direction has the fewest choices.
+struct Search<'a> {
+    pairs: &'a [(Char, Char)],
+    left: [Option<Char>; LEN],
+    right: [Option<Char>; LEN],
+    steps: usize,
+    max_steps: usize,
+}
+
+impl<'a> Search<'a> {
+    // Positions `i` that letter pair `n` could be at, given the slots at each position and that
+    // the right wheel is looked up `offset` positions before the left
+    fn candidates(
+        &self,
+        n: usize,
+        slots: &([usize; LEN], [usize; LEN]),
+        offset: usize,
+    ) -> Vec<usize> {
+        let (p, c) = self.pairs[n];
+        let right_pos = |i: usize| (i + LEN - offset) % LEN;
+        let left_free = |i: usize| self.left[slots.0[i]].is_none();
+        let right_free = |i: usize| self.right[slots.1[right_pos(i)]].is_none();
+
+        let left = (0..LEN).find(|&i| self.left[slots.0[i]] == Some(c));
+        let right = (0..LEN)
+            .find(|&i| self.right[slots.1[i]] == Some(p))
+            .map(|i| (i + offset) % LEN);
+
+        match (right, left) {
+            (Some(i), Some(j)) if i == j => vec![i],
+            (Some(_), Some(_)) => vec![],
+            (Some(i), None) if left_free(i) => vec![i],
+            (None, Some(j)) if right_free(j) => vec![j],
+            (Some(_), None) | (None, Some(_)) => vec![],
+            (None, None) => (0..LEN)
+                .filter(|&i| left_free(i) && right_free(i))
+                .collect(),
+        }
+    }
+
+    fn step(&mut self, window: Window) -> Option<([usize; LEN], [usize; LEN])> {
+        self.steps += 1;
+        if self.steps > self.max_steps {
+            return None;
+        }
+
+        let placed = window.end > window.start;
+        let forward = match window.end < self.pairs.len() {
+            true if placed => Some(self.candidates(window.end, &window.back, 0)),
+            true => Some(vec![0]),
+            false => None,
+        };
+        let backward = match window.start > 0 {
+            true if placed => Some(self.candidates(window.start - 1, &window.front, 1)),
+            _ => None,
+        };
+
+        let (candidates, is_forward) = match (forward, backward) {
+            (None, None) => return Some(window.front),
+            (Some(f), None) => (f, true),
+            (None, Some(b)) => (b, false),
+            (Some(f), Some(b)) if f.len() <= b.len() => (f, true),
+            (Some(_), Some(b)) => (b, false),
+        };
+
+        for i in candidates {
+            let (saved_left, saved_right) = (self.left, self.right);
+            let mut next = window;
+
+            if is_forward {
+                let (p, c) = self.pairs[window.end];
+                let (l, r) = &mut next.back;
+                self.left[l[i]] = Some(c);
+                self.right[r[i]] = Some(p);
+
+                permute_left(l, i);
+                permute_right(r, i);
+                next.end += 1;
+            } else {
+                let (p, c) = self.pairs[window.start - 1];
+                let (l, r) = &mut next.front;
+                self.left[l[i]] = Some(c);
+                self.right[r[(i + LEN - 1) % LEN]] = Some(p);
+
+                l.rotate_left(i);
+                r.rotate_left(i);
+                unpermute_left(l);
+                unpermute_right(r);
+                next.start -= 1;
+            }
+
+            if let Some(front) = self.step(next) {
+                return Some(front);
+            }
+
+            self.left = saved_left;
+            self.right = saved_right;
+        }
+
+        None
+    }
+}
+
+fn fill(wheel: [Option<Char>; LEN]) -> [Char; LEN] {
+    let mut unused = (0..Char::MAX)
+        .map(Char::from)
+        .filter(|c| !wheel.contains(&Some(*c)));
+
+    let mut out = [Char::from(0); LEN];
+    for (o, w) in out.iter_mut().zip(wheel.iter()) {
+        *o = w.unwrap_or_else(|| unused.next().unwrap());
+    }
+
+    out
+}
+
+impl PartialEncrypt for Chaocipher {
+    fn encrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+        Ok(self.run(buf, true))
+    }
+}
+
+impl PartialDecrypt for Chaocipher {
+    fn decrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+        Ok(self.run(buf, false))
+    }
+}
+
+derive_encrypt_decrypt!(Chaocipher, SimpleError);
+
+impl fmt::Display for Chaocipher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let left = self.left.iter().map(|&c| char::from(c)).collect::<String>();
+        let right = self
+            .right
+            .iter()
+            .map(|&c| char::from(c))
+            .collect::<String>();
+        write!(f, "{} {}", left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alphabet;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut chao =
+            Chaocipher::new("HXUCZVAMDSLKPEFJRIGTWOBNYQ", "PTLNBQDEOYSFAVZKGJRIHWXUMC").unwrap();
+
+        let buf = chao
+            .encrypt(Buffer::from("Well done is better than well said"))
+            .unwrap();
+        assert_eq!("Oahq hcny nx tszjrr hjby hqks oujy", buf.to_string());
+
+        // Each call starts again from the original alphabets
+        let buf = chao.decrypt(buf).unwrap();
+        assert_eq!("Well done is better than well said", buf.to_string());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Chaocipher::new("ABC", "PTLNBQDEOYSFAVZKGJRIHWXUMC").is_err());
+        assert!(
+            Chaocipher::new("AACDEFGHIJKLMNOPQRSTUVWXYZ", "PTLNBQDEOYSFAVZKGJRIHWXUMC").is_err()
+        );
+    }
+
+    #[test]
+    fn test_known_plaintext() {
+        let mut chao =
+            Chaocipher::new("HXUCZVAMDSLKPEFJRIGTWOBNYQ", "PTLNBQDEOYSFAVZKGJRIHWXUMC").unwrap();
+        let plaintext = Buffer::from(
+            "Sing long her way size waited end mutual missed myself the little sister one so in \
+             pointed or chicken cheered neither spirits invited marianne and him laughter",
+        );
+        let ciphertext = chao.encrypt(plaintext.clone()).unwrap();
+
+        let mut key = Chaocipher::from_known_plaintext(&plaintext, &ciphertext).unwrap();
+        assert_eq!(
+            ciphertext.to_string(),
+            key.encrypt(plaintext.clone()).unwrap().to_string()
+        );
+
+        // Every letter turns up in the ciphertext, so the left alphabet comes out in full, rotated
+        // to where the search started. The plaintext has no B, J, Q or X, so those are guesses.
+        let alphabets = key.to_string();
+        assert_eq!("LKPEFJRIGTWOBNYQHXUCZVAMDS", &alphabets[..26]);
+
+        let ciphertext = Buffer::from("Oahq");
+        assert!(Chaocipher::from_known_plaintext(&plaintext, &ciphertext).is_err());
+        assert!(
+            Chaocipher::from_known_plaintext(&Buffer::from("AA"), &Buffer::from("XX")).is_err()
+        );
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut chao =
+            Chaocipher::new("HXUCZVAMDSLKPEFJRIGTWOBNYQ", "PTLNBQDEOYSFAVZKGJRIHWXUMC").unwrap();
+        let plaintext = Buffer::from("Well done is better than well said, and the rest follows");
+        let ciphertext = chao.encrypt(plaintext.clone()).unwrap();
+
+        // A few letters garbled part way through leave no key to find
+        let mut garbled = ciphertext.to_string().into_bytes();
+        garbled[20..24].copy_from_slice(b"QQQQ");
+        let garbled = Buffer::from(String::from_utf8(garbled).unwrap().as_str());
+
+        assert!(Chaocipher::from_known_plaintext(&plaintext, &garbled).is_err());
+
+        // Given too few steps to rule every key out, the search stops rather than going on
+        let err = Chaocipher::search(&plaintext, &garbled, 1).unwrap_err();
+        assert_eq!("search gave up", err.as_str());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Hello 2 world!", alphanumeric).unwrap();
+
+        let mut chao =
+            Chaocipher::new("HXUCZVAMDSLKPEFJRIGTWOBNYQ", "PTLNBQDEOYSFAVZKGJRIHWXUMC").unwrap();
+        assert!(chao.encrypt_partial(buf.clone().into()).is_err());
+        assert!(chao.decrypt_partial(buf.into()).is_err());
+    }
+}
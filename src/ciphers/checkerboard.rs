@@ -0,0 +1,219 @@
+use crate::{Buffer, Char, DigitBuffer};
+use simple_error::SimpleError;
+use std::sync::Arc;
+
+const CELLS: usize = 28;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StraddlingCheckerboard {
+    // 8 cells in the top row, followed by the two 10-cell rows
+    cells: [Option<Char>; CELLS],
+    header: [u8; 10],
+    blanks: (usize, usize),
+}
+
+impl StraddlingCheckerboard {
+    // `alphabet` fills the board row by row, skipping the blank positions of the top row.
+    // Letters missing from it are appended in order. Any other non-whitespace character
+    // (e.g. '.' or '/') reserves a cell that has no letter.
+    pub fn new<T: AsRef<str>>(alphabet: T, blanks: (usize, usize)) -> Result<Self, SimpleError> {
+        if blanks.0 >= 10 || blanks.1 >= 10 || blanks.0 == blanks.1 {
+            return Err(SimpleError::new(
+                "blank positions must be two distinct columns between 0 and 9",
+            ));
+        }
+
+        let mut cells = [None; CELLS];
+        let mut seen = [false; Char::MAX as usize];
+        let mut pos = 0;
+
+        for c in alphabet.as_ref().chars().filter(|c| !c.is_whitespace()) {
+            let cell = if c.is_ascii_alphabetic() {
+                let ch = Char::from(c);
+
+                if seen[usize::from(u8::from(ch))] {
+                    continue;
+                }
+
+                seen[usize::from(u8::from(ch))] = true;
+                Some(ch)
+            } else {
+                None
+            };
+
+            if pos == CELLS {
+                return Err(SimpleError::new("checkerboard alphabet is too long"));
+            }
+
+            cells[pos] = cell;
+            pos += 1;
+        }
+
+        for (i, _) in seen.iter().enumerate().filter(|(_, &s)| !s) {
+            if pos == CELLS {
+                return Err(SimpleError::new("checkerboard alphabet is too long"));
+            }
+
+            cells[pos] = Some(Char::from(i as u8));
+            pos += 1;
+        }
+
+        let blanks = (blanks.0.min(blanks.1), blanks.0.max(blanks.1));
+
+        Ok(Self {
+            cells,
+            header: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+            blanks,
+        })
+    }
+
+    // Replaces the column digits (0 to 9 by default) with a permutation, as in the VIC cipher
+    pub fn with_header(mut self, header: [u8; 10]) -> Result<Self, SimpleError> {
+        let mut seen = [false; 10];
+
+        for &d in header.iter() {
+            if d >= 10 || seen[d as usize] {
+                return Err(SimpleError::new("header must be a permutation of 0 to 9"));
+            }
+
+            seen[d as usize] = true;
+        }
+
+        self.header = header;
+        Ok(self)
+    }
+
+    // Returns the cell for a (row, column) pair, where row 0 is the top row
+    fn cell(&self, row: usize, col: usize) -> Option<Char> {
+        match row {
+            0 => {
+                if col == self.blanks.0 || col == self.blanks.1 {
+                    None
+                } else if col < self.blanks.0 {
+                    self.cells[col]
+                } else if col < self.blanks.1 {
+                    self.cells[col - 1]
+                } else {
+                    self.cells[col - 2]
+                }
+            }
+            _ => self.cells[8 + (row - 1) * 10 + col],
+        }
+    }
+
+    fn column(&self, digit: u8) -> usize {
+        self.header.iter().position(|&d| d == digit).unwrap()
+    }
+
+    pub fn encode(&self, buf: &Buffer) -> Result<DigitBuffer, SimpleError> {
+        check_alphabet!(buf, SimpleError);
+
+        let mut table = [(0, 0); Char::MAX as usize];
+
+        for row in 0..3 {
+            for col in 0..10 {
+                if let Some(ch) = self.cell(row, col) {
+                    table[usize::from(u8::from(ch))] = (row, col);
+                }
+            }
+        }
+
+        let mut out = DigitBuffer::default();
+
+        for &c in buf {
+            let (row, col) = table[usize::from(u8::from(c))];
+
+            match row {
+                0 => {}
+                1 => out.push(self.header[self.blanks.0]),
+                _ => out.push(self.header[self.blanks.1]),
+            }
+
+            out.push(self.header[col]);
+        }
+
+        Ok(out)
+    }
+
+    pub fn decode(&self, digits: &DigitBuffer) -> Result<Buffer, SimpleError> {
+        let mut out = Vec::with_capacity(digits.len());
+        let mut iter = digits.into_iter();
+
+        while let Some(&d) = iter.next() {
+            let col = self.column(d);
+
+            let row = if col == self.blanks.0 {
+                1
+            } else if col == self.blanks.1 {
+                2
+            } else {
+                0
+            };
+
+            let col = if row == 0 {
+                col
+            } else {
+                match iter.next() {
+                    Some(&d) => self.column(d),
+                    None => return Err(SimpleError::new("truncated checkerboard code")),
+                }
+            };
+
+            match self.cell(row, col) {
+                Some(ch) => out.push(ch),
+                None => {
+                    return Err(SimpleError::new(
+                        "code refers to an empty checkerboard cell",
+                    ))
+                }
+            }
+        }
+
+        let original = out.iter().map(|&c| char::from(c)).collect::<String>();
+        Ok(Buffer::new(out, Arc::new(original)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alphabet;
+
+    #[test]
+    fn test_encode_decode() {
+        let board = StraddlingCheckerboard::new("ETAONRISBCDFGHJKLMPQ/UVWXYZ.", (2, 6)).unwrap();
+        let buf = Buffer::from("Attack at dawn");
+
+        let digits = board.encode(&buf).unwrap();
+        assert_eq!(DigitBuffer::from("3113212731223655"), digits);
+
+        let buf = board.decode(&digits).unwrap();
+        assert_eq!("ATTACKATDAWN", buf.to_string());
+    }
+
+    #[test]
+    fn test_header() {
+        let board = StraddlingCheckerboard::new("ESTONIAR", (3, 7))
+            .unwrap()
+            .with_header([5, 0, 9, 2, 4, 6, 8, 1, 3, 7])
+            .unwrap();
+        let buf = Buffer::from("WEMEETATMIDNIGHT");
+
+        let digits = board.encode(&buf).unwrap();
+        assert_eq!(
+            "WEMEETATMIDNIGHT",
+            board.decode(&digits).unwrap().to_string()
+        );
+
+        assert!(board.decode(&DigitBuffer::from("2")).is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let board = StraddlingCheckerboard::new("ETAONRISBCDFGHJKLMPQ/UVWXYZ.", (2, 6)).unwrap();
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Attack at 0900", alphanumeric).unwrap();
+
+        assert!(board.encode(&buf).is_err());
+    }
+}
@@ -0,0 +1,559 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use crate::{Buffer, Char, PartialBuffer};
+use simple_error::SimpleError;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RotorType {
+    I,
+    II,
+    III,
+    IV,
+    V,
+    VI,
+    VII,
+    VIII,
+    // Greek wheels, only used in the fourth (leftmost) slot of the M4
+    Beta,
+    Gamma,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Reflector {
+    B,
+    C,
+    // Thin reflectors, only used by the M4
+    BThin,
+    CThin,
+}
+
+impl RotorType {
+    pub const ALL: [RotorType; 8] = [
+        RotorType::I,
+        RotorType::II,
+        RotorType::III,
+        RotorType::IV,
+        RotorType::V,
+        RotorType::VI,
+        RotorType::VII,
+        RotorType::VIII,
+    ];
+
+    fn wiring(self) -> &'static str {
+        match self {
+            RotorType::I => "EKMFLGDQVZNTOWYHXUSPAIBRCJ",
+            RotorType::II => "AJDKSIRUXBLHWTMCQGZNPYFVOE",
+            RotorType::III => "BDFHJLCPRTXVZNYEIWGAKMOUSQ",
+            RotorType::IV => "ESOVPZJAYQUIRHXLNFTGKDCMWB",
+            RotorType::V => "VZBRGITYUPSDNHLXAWMJQOFECK",
+            RotorType::VI => "JPGVOUMFYQBENHZRDKASXLICTW",
+            RotorType::VII => "NZJHGRCXMYSWBOUFAIVLPEKQDT",
+            RotorType::VIII => "FKQHTLXOCBJSPDZRAMEWNIUYGV",
+            RotorType::Beta => "LEYJVCNIXWPBQMDRTAKZGFUHOS",
+            RotorType::Gamma => "FSOKANUERHMBTIYCWLQPZXVGJD",
+        }
+    }
+
+    fn notches(self) -> &'static str {
+        match self {
+            RotorType::I => "Q",
+            RotorType::II => "E",
+            RotorType::III => "V",
+            RotorType::IV => "J",
+            RotorType::V => "Z",
+            RotorType::VI | RotorType::VII | RotorType::VIII => "ZM",
+            RotorType::Beta | RotorType::Gamma => "",
+        }
+    }
+
+    pub fn is_greek(self) -> bool {
+        self == RotorType::Beta || self == RotorType::Gamma
+    }
+
+    // The contact each contact is wired to, with the ring at A
+    pub(crate) fn forward(self) -> [u8; Char::MAX as usize] {
+        parse_wiring(self.wiring())
+    }
+
+    // Whether the rotor turns the next one over as it steps on from this position
+    pub(crate) fn is_notch(self, pos: u8) -> bool {
+        self.notches().bytes().any(|c| c - b'A' == pos)
+    }
+}
+
+impl Reflector {
+    fn wiring(self) -> &'static str {
+        match self {
+            Reflector::B => "YRUHQSLDPXNGOKMIEBFZCWVJAT",
+            Reflector::C => "FVPJIAOYEDRZXWGCTKUQSBNMHL",
+            Reflector::BThin => "ENKQAUYWJICOPBLMDXZVFTHRGS",
+            Reflector::CThin => "RDOBJNTKVEHMLFCWZAXGYIPSUQ",
+        }
+    }
+
+    pub fn is_thin(self) -> bool {
+        self == Reflector::BThin || self == Reflector::CThin
+    }
+
+    pub(crate) fn table(self) -> [u8; Char::MAX as usize] {
+        parse_wiring(self.wiring())
+    }
+}
+
+fn parse_wiring(wiring: &str) -> [u8; Char::MAX as usize] {
+    let mut out = [0; Char::MAX as usize];
+
+    for (i, c) in wiring.bytes().enumerate() {
+        out[i] = c - b'A';
+    }
+
+    out
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rotor {
+    kind: RotorType,
+    forward: [u8; Char::MAX as usize],
+    backward: [u8; Char::MAX as usize],
+    notches: [bool; Char::MAX as usize],
+    ring: u8,
+    pos: u8,
+}
+
+impl Rotor {
+    fn new(kind: RotorType, ring: u8, pos: u8) -> Self {
+        let forward = parse_wiring(kind.wiring());
+        let mut backward = [0; Char::MAX as usize];
+        for (i, &c) in forward.iter().enumerate() {
+            backward[c as usize] = i as u8;
+        }
+
+        let mut notches = [false; Char::MAX as usize];
+        for c in kind.notches().bytes() {
+            notches[(c - b'A') as usize] = true;
+        }
+
+        Self {
+            kind,
+            forward,
+            backward,
+            notches,
+            ring,
+            pos,
+        }
+    }
+
+    fn at_notch(&self) -> bool {
+        self.notches[self.pos as usize]
+    }
+
+    fn step(&mut self) {
+        self.pos = (self.pos + 1) % Char::MAX;
+    }
+
+    fn map(&self, c: u8, table: &[u8; Char::MAX as usize]) -> u8 {
+        let shift = (self.pos + Char::MAX - self.ring) % Char::MAX;
+        let out = table[((c + shift) % Char::MAX) as usize];
+        (out + Char::MAX - shift) % Char::MAX
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enigma {
+    reflector: Reflector,
+    reflector_wiring: [u8; Char::MAX as usize],
+    // The M4's non-stepping fourth rotor
+    greek: Option<Rotor>,
+    // Left, middle and right rotors
+    rotors: [Rotor; 3],
+    plugboard: [u8; Char::MAX as usize],
+    // Rotor positions (including the greek wheel's) at the start of each message
+    start: [u8; 4],
+}
+
+impl Enigma {
+    // Rotors, rings and positions are given from left to right. With four rotors the machine is
+    // an M4, and the first rotor must be a greek wheel used with a thin reflector.
+    // Ring settings and positions are zero based (i.e. 0 is A or 01).
+    pub fn new(
+        reflector: Reflector,
+        rotors: &[RotorType],
+        rings: &[u8],
+        positions: &[u8],
+        plugboard: &[(Char, Char)],
+    ) -> Result<Self, SimpleError> {
+        if rotors.len() != rings.len() || rotors.len() != positions.len() {
+            return Err(SimpleError::new(
+                "there must be a ring setting and position for every rotor",
+            ));
+        }
+
+        if rings.iter().chain(positions).any(|&x| x >= Char::MAX) {
+            return Err(SimpleError::new(
+                "ring settings and positions must be below 26",
+            ));
+        }
+
+        let (greek, rest) = match rotors.len() {
+            3 if !reflector.is_thin() => (None, 0),
+            4 if reflector.is_thin() && rotors[0].is_greek() => {
+                (Some(Rotor::new(rotors[0], rings[0], positions[0])), 1)
+            }
+            3 | 4 => {
+                return Err(SimpleError::new(
+                    "an M3 needs a B or C reflector, and an M4 needs a thin reflector and a greek wheel",
+                ))
+            }
+            _ => return Err(SimpleError::new("the machine must have 3 or 4 rotors")),
+        };
+
+        let kinds = &rotors[rest..];
+        if kinds.iter().any(|r| r.is_greek()) {
+            return Err(SimpleError::new(
+                "greek wheels may only be used in the fourth slot",
+            ));
+        }
+
+        if (0..3).any(|i| kinds[i + 1..].contains(&kinds[i])) {
+            return Err(SimpleError::new("each rotor may only be used once"));
+        }
+
+        let mut board = [0; Char::MAX as usize];
+        for (i, b) in board.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        for &(a, b) in plugboard {
+            let (a, b) = (u8::from(a) as usize, u8::from(b) as usize);
+
+            if a == b || board[a] != a as u8 || board[b] != b as u8 {
+                return Err(SimpleError::new("plugboard letters may only be used once"));
+            }
+
+            board[a] = b as u8;
+            board[b] = a as u8;
+        }
+
+        let rotor = |i: usize| Rotor::new(kinds[i], rings[rest + i], positions[rest + i]);
+        let mut start = [0; 4];
+        start[4 - positions.len()..].copy_from_slice(positions);
+
+        Ok(Self {
+            reflector,
+            reflector_wiring: parse_wiring(reflector.wiring()),
+            greek,
+            rotors: [rotor(0), rotor(1), rotor(2)],
+            plugboard: board,
+            start,
+        })
+    }
+
+    pub fn reflector(&self) -> Reflector {
+        self.reflector
+    }
+
+    pub fn rotor_types(&self) -> Vec<RotorType> {
+        self.greek
+            .iter()
+            .chain(self.rotors.iter())
+            .map(|r| r.kind)
+            .collect()
+    }
+
+    // Current positions from left to right, including the greek wheel if there is one
+    pub fn positions(&self) -> Vec<u8> {
+        self.greek
+            .iter()
+            .chain(self.rotors.iter())
+            .map(|r| r.pos)
+            .collect()
+    }
+
+    // Positions each message starts from (the message key)
+    pub fn start_positions(&self) -> Vec<u8> {
+        self.start[4 - self.num_rotors()..].to_vec()
+    }
+
+    fn num_rotors(&self) -> usize {
+        self.greek.iter().count() + self.rotors.len()
+    }
+
+    // One position per rotor, the greek wheel's first
+    pub fn set_positions(&mut self, positions: &[u8]) {
+        let n = self.num_rotors();
+        assert_eq!(n, positions.len(), "expected {} rotor positions", n);
+
+        let rotors = self.greek.iter_mut().chain(self.rotors.iter_mut());
+
+        for (r, &p) in rotors.zip(positions) {
+            r.pos = p % Char::MAX;
+        }
+
+        for (s, &p) in self.start[4 - n..].iter_mut().zip(positions) {
+            *s = p % Char::MAX;
+        }
+    }
+
+    // One ring setting per rotor, the greek wheel's first
+    pub fn set_rings(&mut self, rings: &[u8]) {
+        let n = self.num_rotors();
+        assert_eq!(n, rings.len(), "expected {} ring settings", n);
+
+        let rotors = self.greek.iter_mut().chain(self.rotors.iter_mut());
+
+        for (r, &ring) in rotors.zip(rings) {
+            r.ring = ring % Char::MAX;
+        }
+    }
+
+    pub fn rings(&self) -> Vec<u8> {
+        self.greek
+            .iter()
+            .chain(self.rotors.iter())
+            .map(|r| r.ring)
+            .collect()
+    }
+
+    pub fn plugboard(&self) -> &[u8; Char::MAX as usize] {
+        &self.plugboard
+    }
+
+    // Replaces the plugboard wholesale; `board` must be an involution
+    pub fn set_plugboard(&mut self, board: [u8; Char::MAX as usize]) {
+        debug_assert!((0..Char::MAX as usize).all(|i| board[board[i] as usize] as usize == i));
+        self.plugboard = board;
+    }
+
+    fn reset(&mut self) {
+        let start = self.start;
+        let rotors = self.greek.iter_mut().chain(self.rotors.iter_mut());
+
+        for (r, &p) in rotors.rev().zip(start.iter().rev()) {
+            r.pos = p;
+        }
+    }
+
+    fn step(&mut self) {
+        // The middle rotor steps itself when it's at its notch (the double step)
+        if self.rotors[1].at_notch() {
+            self.rotors[1].step();
+            self.rotors[0].step();
+        } else if self.rotors[2].at_notch() {
+            self.rotors[1].step();
+        }
+
+        self.rotors[2].step();
+    }
+
+    // Presses a single key
+    pub fn press(&mut self, c: Char) -> Char {
+        self.step();
+
+        let mut c = self.plugboard[u8::from(c) as usize];
+
+        for r in self.rotors.iter().rev() {
+            c = r.map(c, &r.forward);
+        }
+        if let Some(g) = &self.greek {
+            c = g.map(c, &g.forward);
+        }
+
+        c = self.reflector_wiring[c as usize];
+
+        if let Some(g) = &self.greek {
+            c = g.map(c, &g.backward);
+        }
+        for r in self.rotors.iter() {
+            c = r.map(c, &r.backward);
+        }
+
+        Char::from(self.plugboard[c as usize])
+    }
+}
+
+fn parse_letters(s: &str) -> Result<Vec<u8>, SimpleError> {
+    // Either letters ("BUL") or one based numbers ("02,21,12")
+    if s.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(s.chars().map(|c| u8::from(Char::from(c))).collect())
+    } else {
+        s.split(&[',', '-'][..])
+            .map(|n| match n.parse::<u8>() {
+                Ok(n) if (1..=Char::MAX).contains(&n) => Ok(n - 1),
+                _ => Err(SimpleError::new(format!("invalid setting '{}'", n))),
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Enigma {
+    type Err = SimpleError;
+
+    // Key spec: reflector, rotors, rings, positions and then any plugboard pairs, e.g.
+    // "B II,IV,V 02,21,12 BLA AV BS CG DL FU HZ IN KM OW RX"
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut fields = spec.split_whitespace();
+        let mut next = |name: &str| {
+            fields
+                .next()
+                .ok_or_else(|| SimpleError::new(format!("enigma key is missing the {}", name)))
+        };
+
+        let reflector = match next("reflector")?.to_ascii_uppercase().as_str() {
+            "B" => Reflector::B,
+            "C" => Reflector::C,
+            "BTHIN" | "B-THIN" => Reflector::BThin,
+            "CTHIN" | "C-THIN" => Reflector::CThin,
+            r => return Err(SimpleError::new(format!("unknown reflector '{}'", r))),
+        };
+
+        let rotors = next("rotors")?
+            .split(&[',', '-'][..])
+            .map(|r| match r.to_ascii_uppercase().as_str() {
+                "I" => Ok(RotorType::I),
+                "II" => Ok(RotorType::II),
+                "III" => Ok(RotorType::III),
+                "IV" => Ok(RotorType::IV),
+                "V" => Ok(RotorType::V),
+                "VI" => Ok(RotorType::VI),
+                "VII" => Ok(RotorType::VII),
+                "VIII" => Ok(RotorType::VIII),
+                "BETA" => Ok(RotorType::Beta),
+                "GAMMA" => Ok(RotorType::Gamma),
+                _ => Err(SimpleError::new(format!("unknown rotor '{}'", r))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let rings = parse_letters(next("ring settings")?)?;
+        let positions = parse_letters(next("positions")?)?;
+
+        let plugboard = fields
+            .map(|pair| {
+                let pair = pair.chars().collect::<Vec<_>>();
+
+                if pair.len() == 2 && pair.iter().all(|c| c.is_ascii_alphabetic()) {
+                    Ok((Char::from(pair[0]), Char::from(pair[1])))
+                } else {
+                    Err(SimpleError::new("plugboard pairs must be two letters"))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Enigma::new(reflector, &rotors, &rings, &positions, &plugboard)
+    }
+}
+
+impl PartialEncrypt for Enigma {
+    // Every message starts from the configured rotor positions
+    fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        self.reset();
+
+        for c in &mut buf {
+            *c = self.press(*c);
+        }
+
+        Ok(buf)
+    }
+}
+
+impl PartialDecrypt for Enigma {
+    // The machine is reciprocal
+    fn decrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        self.encrypt_partial(buf)
+    }
+}
+
+derive_encrypt_decrypt!(Enigma, SimpleError);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut enigma = Enigma::from_str("B I,II,III AAA AAA").unwrap();
+
+        let buf = enigma.encrypt(Buffer::from("AAAAA")).unwrap();
+        assert_eq!("BDZGO", buf.to_string());
+
+        let buf = enigma.encrypt(Buffer::from("Hello world")).unwrap();
+        let buf = enigma.decrypt(buf).unwrap();
+        assert_eq!("Hello world", buf.to_string());
+    }
+
+    #[test]
+    fn test_double_step() {
+        let mut enigma = Enigma::from_str("B I,II,III AAA ADU").unwrap();
+        let mut positions = Vec::new();
+
+        for _ in 0..4 {
+            enigma.press(Char::from('A'));
+            positions.push(enigma.positions());
+        }
+
+        let expected = ["ADV", "AEW", "BFX", "BFY"]
+            .iter()
+            .map(|s| parse_letters(s).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, positions);
+    }
+
+    #[test]
+    fn test_barbarossa() {
+        // The first part of a 1941 army message
+        let mut enigma =
+            Enigma::from_str("B II,IV,V 02,21,12 BLA AV BS CG DL FU HZ IN KM OW RX").unwrap();
+        let buf = Buffer::from(
+            "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM \
+             YLKLT TDEIS MDICA GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ \
+             VIJHI DISHP RKLKA YUPAD",
+        );
+
+        let buf = enigma.decrypt(buf).unwrap();
+        assert_eq!(
+            "AUFKL XABTE ILUNG XVONX KURTI NOWAX KURTI NOWAX NORDW ESTLX SEBEZ XSEBE \
+             ZXUAF FLIEG ERSTR ASZER IQTUN GXDUB ROWKI XDUBR OWKIX OPOTS CHKAX OPOTS \
+             CHKAX UMXEI NSAQT DREIN",
+            buf.to_string()
+        );
+    }
+
+    #[test]
+    fn test_m4() {
+        // A thin reflector with the beta wheel at A is equivalent to the B reflector
+        let mut m3 = Enigma::from_str("B I,II,III AAA AAA AB CD").unwrap();
+        let mut m4 = Enigma::from_str("Bthin Beta,I,II,III AAAA AAAA AB CD").unwrap();
+
+        let text = "The quick brown fox jumps over the lazy dog";
+        let buf = m4.encrypt(Buffer::from(text)).unwrap();
+        assert_eq!(
+            m3.encrypt(Buffer::from(text)).unwrap().to_string(),
+            buf.to_string()
+        );
+
+        let mut m4 = Enigma::from_str("Cthin Gamma,VI,VII,VIII AQEV BBCD AZ").unwrap();
+        let buf = m4.encrypt(Buffer::from(text)).unwrap();
+        assert_eq!(text, m4.decrypt(buf).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(Enigma::from_str("B I,I,III AAA AAA").is_err());
+        assert!(Enigma::from_str("Bthin I,II,III AAA AAA").is_err());
+        assert!(Enigma::from_str("B Beta,I,II,III AAAA AAAA").is_err());
+        assert!(Enigma::from_str("B I,II,III AAA AAA AB AC").is_err());
+        assert!(Enigma::from_str("B I,II,III AAA").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 3 rotor positions")]
+    fn test_set_positions() {
+        let mut enigma = Enigma::from_str("B I,II,III AAA AAA").unwrap();
+
+        enigma.set_positions(&[1, 2, 29]);
+        assert_eq!(vec![1, 2, 3], enigma.start_positions());
+
+        enigma.set_positions(&[0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Decrypt, Encrypt, Substitution};
+use crate::meta::HeuristicTarget;
+use crate::morse::{MorseBuffer, Symbol};
+use crate::{Buffer, Char};
+use rand::{seq::SliceRandom, Rng};
+use simple_error::SimpleError;
+use std::fmt;
+use std::sync::Arc;
+
+const SYMBOLS: [Symbol; 3] = [Symbol::Dot, Symbol::Dash, Symbol::Separator];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FractionatedMorse {
+    // The letter standing for each trigraph, in the order ..., ..-, ..x, .-. and so on
+    key: [Char; Char::MAX as usize],
+}
+
+impl FractionatedMorse {
+    pub fn new<T: AsRef<str>>(keyword: T) -> Self {
+        Self {
+            key: Substitution::keyed_alphabet(keyword.as_ref()),
+        }
+    }
+
+    fn trigraph(index: usize) -> [Symbol; 3] {
+        [
+            SYMBOLS[index / 9],
+            SYMBOLS[(index / 3) % 3],
+            SYMBOLS[index % 3],
+        ]
+    }
+}
+
+impl Encrypt for FractionatedMorse {
+    type Error = SimpleError;
+
+    fn encrypt(&mut self, buf: Buffer) -> Result<Buffer, Self::Error> {
+        let mut morse = MorseBuffer::encode(&buf)?;
+        morse.pad_to_multiple(3);
+
+        let data = morse
+            .as_slice()
+            .chunks(3)
+            .map(|t| {
+                let index = t.iter().fold(0, |acc, s| {
+                    acc * 3 + SYMBOLS.iter().position(|x| x == s).unwrap()
+                });
+
+                self.key
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| SimpleError::new("morse contains three separators in a row"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let original = data.iter().map(|&c| char::from(c)).collect::<String>();
+        Ok(Buffer::new(data, Arc::new(original)))
+    }
+}
+
+impl Decrypt for FractionatedMorse {
+    type Error = SimpleError;
+
+    // Sequences that are not valid Morse decode as X (see `MorseBuffer::decode_lossy`)
+    fn decrypt(&mut self, buf: Buffer) -> Result<Buffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        let inverse = Substitution::compute_inverse(self.key);
+
+        let morse = buf
+            .into_iter()
+            .flat_map(|&c| {
+                FractionatedMorse::trigraph(usize::from(u8::from(
+                    inverse[usize::from(u8::from(c))],
+                )))
+                .to_vec()
+            })
+            .collect::<Vec<_>>();
+
+        Ok(MorseBuffer::new(morse).decode_lossy())
+    }
+}
+
+impl fmt::Display for FractionatedMorse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let out = self.key.iter().map(|&c| char::from(c)).collect::<String>();
+        write!(f, "{}", out)
+    }
+}
+
+impl HeuristicTarget for FractionatedMorse {
+    type KeyParam = ();
+
+    fn rand_key<R: Rng + ?Sized>(_param: Self::KeyParam, rng: &mut R) -> Self {
+        let mut key = Substitution::keyed_alphabet("");
+        key.shuffle(rng);
+
+        Self { key }
+    }
+
+    fn tweak_key<R: Rng + ?Sized>(&self, _param: Self::KeyParam, rng: &mut R) -> Self {
+        let mut s = self.clone();
+        let c1 = rng.gen_range(0, Char::MAX as usize);
+        let c2 = rng.gen_range(0, Char::MAX as usize);
+        s.key.swap(c1, c2);
+        s
+    }
+
+    // There are 26! keys, too many to count through, so brute force finds none
+    fn next_key(_key: Option<Self>, _param: Self::KeyParam) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::score::ScoreMethod;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut fm = FractionatedMorse::new("ROUNDTABLE");
+        let buf = Buffer::from("Come at once");
+
+        let buf = fm.encrypt(buf).unwrap();
+        assert_eq!("CBIILTMHVVFL", buf.to_string());
+
+        let buf = fm.decrypt(buf).unwrap();
+        assert_eq!("COME AT ONCE", buf.to_string());
+    }
+
+    #[test]
+    fn test_heuristic() {
+        let plaintext = Buffer::from("The quick brown fox jumps over the lazy dog and runs away");
+        let mut key = FractionatedMorse::new("ROUNDTABLE");
+        let ciphertext = key.encrypt(plaintext).unwrap();
+
+        let best = key
+            .decrypt(ciphertext.clone())
+            .unwrap()
+            .score(ScoreMethod::Quadgrams);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..20 {
+            let mut other = FractionatedMorse::rand_key((), &mut rng);
+            let score = other
+                .decrypt(ciphertext.clone())
+                .unwrap()
+                .score(ScoreMethod::Quadgrams);
+            assert!(score < best);
+
+            let tweaked = other.tweak_key((), &mut rng);
+            let mut letters = tweaked.key.to_vec();
+            letters.sort();
+            assert_eq!(Substitution::keyed_alphabet("").to_vec(), letters);
+        }
+
+        assert!(FractionatedMorse::next_key(None, ()).is_none());
+    }
+}
@@ -0,0 +1,205 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt, Resumable};
+use crate::meta::HeuristicTarget;
+use crate::{Buffer, Char, PartialBuffer};
+use rand::Rng;
+use simple_error::SimpleError;
+use smallvec::SmallVec;
+use std::fmt;
+
+// A Vigenère cipher keyed with numbers rather than letters, where 0 leaves a letter unchanged.
+// Classically the key is a string of digits, so every shift is below 10.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gronsfeld {
+    shifts: SmallVec<[u8; 32]>,
+    // Where in the shifts the next letter starts, once earlier pieces have been skipped
+    offset: usize,
+}
+
+impl Gronsfeld {
+    // Takes either a run of digits such as "31415", each digit being one shift, or numbers split
+    // by commas or spaces such as "3,14,15", each number being one shift below 26
+    pub fn new(key: &str) -> Result<Self, SimpleError> {
+        let key = key.trim();
+        let separated = key.contains(|c: char| c == ',' || c.is_whitespace());
+
+        let shifts = if separated {
+            key.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .map(|s| match s.parse::<u8>() {
+                    Ok(n) if n < Char::MAX => Ok(n),
+                    _ => Err(SimpleError::new(format!("invalid shift '{}'", s))),
+                })
+                .collect::<Result<SmallVec<[u8; 32]>, _>>()?
+        } else {
+            key.chars()
+                .map(|c| match c.to_digit(10) {
+                    Some(d) => Ok(d as u8),
+                    None => Err(SimpleError::new(format!("invalid digit '{}'", c))),
+                })
+                .collect::<Result<SmallVec<[u8; 32]>, _>>()?
+        };
+
+        if shifts.is_empty() {
+            return Err(SimpleError::new("key must contain at least one shift"));
+        }
+
+        Ok(Self { shifts, offset: 0 })
+    }
+
+    pub fn period(&self) -> usize {
+        self.shifts.len()
+    }
+
+    pub fn shifts(&self) -> &[u8] {
+        &self.shifts
+    }
+}
+
+impl PartialEncrypt for Gronsfeld {
+    fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        let keylen = self.shifts.len();
+
+        for (i, b) in (&mut buf).into_iter().enumerate() {
+            *b += self.shifts[(i + self.offset) % keylen];
+        }
+
+        Ok(buf)
+    }
+}
+
+impl PartialDecrypt for Gronsfeld {
+    fn decrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        let keylen = self.shifts.len();
+
+        for (i, b) in (&mut buf).into_iter().enumerate() {
+            *b -= self.shifts[(i + self.offset) % keylen];
+        }
+
+        Ok(buf)
+    }
+}
+
+derive_encrypt_decrypt!(Gronsfeld, SimpleError);
+
+impl Resumable for Gronsfeld {
+    fn skip(&mut self, n: usize) {
+        self.offset = (self.offset + n) % self.shifts.len();
+    }
+}
+
+// Written back in whichever form `new` would read it
+impl fmt::Display for Gronsfeld {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shifts = self.shifts.iter().map(|s| s.to_string());
+
+        if self.shifts.iter().all(|&s| s < 10) {
+            write!(f, "{}", shifts.collect::<String>())
+        } else {
+            write!(f, "{}", shifts.collect::<Vec<_>>().join(","))
+        }
+    }
+}
+
+// The key param is the period. Only digit keys are searched, so a full search of a period n key
+// takes 10^n decryptions.
+impl HeuristicTarget for Gronsfeld {
+    type KeyParam = usize;
+
+    fn rand_key<R: Rng + ?Sized>(param: Self::KeyParam, rng: &mut R) -> Self {
+        assert!(param > 0, "period was zero");
+
+        Self {
+            shifts: (0..param).map(|_| rng.gen_range(0, 10)).collect(),
+            offset: 0,
+        }
+    }
+
+    fn tweak_key<R: Rng + ?Sized>(&self, _param: Self::KeyParam, rng: &mut R) -> Self {
+        let mut s = self.clone();
+        let i = rng.gen_range(0, s.shifts.len());
+        s.shifts[i] = (s.shifts[i] + rng.gen_range(1, 10)) % 10;
+        s
+    }
+
+    fn next_key(key: Option<Self>, param: Self::KeyParam) -> Option<Self> {
+        let mut key = match key {
+            Some(k) => k,
+            None => {
+                return Some(Self {
+                    shifts: SmallVec::from_elem(0, param),
+                    offset: 0,
+                })
+            }
+        };
+
+        for shift in key.shifts.iter_mut().rev() {
+            *shift += 1;
+            if *shift < 10 {
+                return Some(key);
+            }
+
+            *shift = 0;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::brute::BruteForce;
+    use crate::meta::Metaheuristic;
+    use crate::score::ScoreMethod;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut gronsfeld = Gronsfeld::new("31415").unwrap();
+        let buf = Buffer::from("Hello world!");
+
+        let buf = gronsfeld.encrypt(buf).unwrap();
+        assert_eq!("Kfpmt zpvmi!", buf.to_string());
+
+        let buf = gronsfeld.decrypt(buf).unwrap();
+        assert_eq!("Hello world!", buf.to_string());
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut gronsfeld = Gronsfeld::new("31415").unwrap();
+        gronsfeld.skip(2);
+
+        let buf = gronsfeld.encrypt(Buffer::from("llo")).unwrap();
+        assert_eq!("pmt", buf.to_string());
+        assert_eq!("31415", gronsfeld.to_string());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(&[3, 1, 4, 1, 5], Gronsfeld::new("31415").unwrap().shifts());
+        assert_eq!(&[1, 2, 3], Gronsfeld::new("1,2,3").unwrap().shifts());
+        assert_eq!(&[3, 14, 15], Gronsfeld::new("3, 14 15").unwrap().shifts());
+        assert_eq!("3,14,15", Gronsfeld::new("3,14,15").unwrap().to_string());
+        assert_eq!("123", Gronsfeld::new("1,2,3").unwrap().to_string());
+
+        assert!(Gronsfeld::new("").is_err());
+        assert!(Gronsfeld::new("12a").is_err());
+        assert!(Gronsfeld::new("1,26").is_err());
+    }
+
+    #[test]
+    fn test_brute() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI"
+            .into();
+        let mut key = Gronsfeld::new("2718").unwrap();
+        let ciphertext = key.encrypt(plaintext.clone()).unwrap();
+
+        let results = BruteForce::new()
+            .crack_ciphertext::<Gronsfeld>(ciphertext, 4, ScoreMethod::Quadgrams, 1)
+            .unwrap();
+
+        assert_eq!(results[0].buf, plaintext);
+        assert_eq!(key, results[0].key);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{stats, Buffer, Char, DigitBuffer};
+use rand::{seq::SliceRandom, Rng};
+use simple_error::SimpleError;
+use std::fmt;
+use std::sync::Arc;
+
+// Symbols are written as two digit numbers, so there can be at most 100 of them
+pub const MAX_SYMBOLS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Homophonic {
+    // The plaintext letter that each ciphertext symbol stands for
+    symbols: Vec<Char>,
+}
+
+impl Homophonic {
+    pub fn new(symbols: Vec<Char>) -> Result<Self, SimpleError> {
+        if symbols.is_empty() || symbols.len() > MAX_SYMBOLS {
+            return Err(SimpleError::new(
+                "number of symbols must be between 1 and 100",
+            ));
+        }
+
+        Ok(Self { symbols })
+    }
+
+    // Number of homophones each letter gets, proportional to its english frequency (but at least one)
+    pub fn allocate(num_symbols: usize) -> [usize; Char::MAX as usize] {
+        assert!(
+            num_symbols >= Char::MAX as usize && num_symbols <= MAX_SYMBOLS,
+            "number of symbols must be between 26 and 100"
+        );
+
+        let mut counts = [1; Char::MAX as usize];
+        let spare = (num_symbols - Char::MAX as usize) as f64;
+
+        // Largest remainder method, so that the counts add up to exactly num_symbols
+        let mut remainders = stats::ENGLISH_FREQUENCIES
+            .iter()
+            .enumerate()
+            .map(|(i, &f)| {
+                let share = f * spare;
+                counts[i] += share as usize;
+                (i, share - share.floor())
+            })
+            .collect::<Vec<_>>();
+
+        remainders.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        let allocated = counts.iter().sum::<usize>();
+        for &(i, _) in remainders.iter().take(num_symbols - allocated) {
+            counts[i] += 1;
+        }
+
+        counts
+    }
+
+    pub fn rand_key<R: Rng + ?Sized>(num_symbols: usize, rng: &mut R) -> Self {
+        let mut symbols = Homophonic::allocate(num_symbols)
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &n)| std::iter::repeat_n(Char::from(i as u8), n))
+            .collect::<Vec<_>>();
+
+        symbols.shuffle(rng);
+
+        Self { symbols }
+    }
+
+    pub fn num_symbols(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn symbol(&self, symbol: usize) -> Char {
+        self.symbols[symbol]
+    }
+
+    pub fn set_symbol(&mut self, symbol: usize, c: Char) {
+        self.symbols[symbol] = c;
+    }
+
+    pub fn swap_symbols(&mut self, a: usize, b: usize) {
+        self.symbols.swap(a, b);
+    }
+
+    pub fn homophones(&self, c: Char) -> impl Iterator<Item = usize> + '_ {
+        self.symbols
+            .iter()
+            .enumerate()
+            .filter(move |&(_, &s)| s == c)
+            .map(|(i, _)| i)
+    }
+
+    // Homophones are used in turn, which flattens the ciphertext symbol frequencies
+    pub fn encrypt(&self, buf: &Buffer) -> Result<DigitBuffer, SimpleError> {
+        check_alphabet!(buf, SimpleError);
+
+        let table = (0..Char::MAX)
+            .map(|i| self.homophones(Char::from(i)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut used = [0; Char::MAX as usize];
+        let mut out = DigitBuffer::default();
+
+        for &c in buf {
+            let i = usize::from(u8::from(c));
+
+            if table[i].is_empty() {
+                return Err(SimpleError::new(format!(
+                    "no homophones for letter {}",
+                    char::from(c)
+                )));
+            }
+
+            let symbol = table[i][used[i] % table[i].len()];
+            used[i] += 1;
+
+            out.push((symbol / 10) as u8);
+            out.push((symbol % 10) as u8);
+        }
+
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, digits: &DigitBuffer) -> Result<Buffer, SimpleError> {
+        let data = parse_symbols(digits)?
+            .into_iter()
+            .map(|s| {
+                self.symbols
+                    .get(s)
+                    .cloned()
+                    .ok_or_else(|| SimpleError::new(format!("unknown symbol {:02}", s)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let original = data.iter().map(|&c| char::from(c)).collect::<String>();
+        Ok(Buffer::new(data, Arc::new(original)))
+    }
+}
+
+// Splits a digit stream into two digit symbols
+pub fn parse_symbols(digits: &DigitBuffer) -> Result<Vec<usize>, SimpleError> {
+    if !digits.len().is_multiple_of(2) {
+        return Err(SimpleError::new("ciphertext has an odd number of digits"));
+    }
+
+    Ok(digits
+        .as_slice()
+        .chunks(2)
+        .map(|pair| pair[0] as usize * 10 + pair[1] as usize)
+        .collect())
+}
+
+impl fmt::Display for Homophonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for i in 0..Char::MAX {
+            let c = Char::from(i);
+            write!(f, "{}:", char::from(c))?;
+
+            for symbol in self.homophones(c) {
+                write!(f, " {:02}", symbol)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alphabet;
+
+    #[test]
+    fn test_allocate() {
+        for &n in &[26, 40, 64, 100] {
+            let counts = Homophonic::allocate(n);
+
+            assert_eq!(n, counts.iter().sum::<usize>());
+            assert!(counts.iter().all(|&c| c >= 1));
+        }
+
+        let counts = Homophonic::allocate(100);
+        assert!(counts[4] > counts[25]); // E gets more homophones than Z
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = Homophonic::rand_key(60, &mut rand::thread_rng());
+        let buf = Buffer::from("Meet me by the old bridge at eleven");
+
+        let digits = key.encrypt(&buf).unwrap();
+        assert_eq!(buf.len() * 2, digits.len());
+
+        let out = key.decrypt(&digits).unwrap();
+        assert_eq!("MEETMEBYTHEOLDBRIDGEATELEVEN", out.to_string());
+
+        assert!(key.decrypt(&DigitBuffer::from("123")).is_err());
+        assert!(key.decrypt(&DigitBuffer::from("99")).is_err());
+    }
+
+    #[test]
+    fn test_homophones_rotate() {
+        let key = Homophonic::new((0..52).map(|i| Char::from((i % 26) as u8)).collect()).unwrap();
+        let buf = Buffer::from("AAA");
+
+        assert_eq!(DigitBuffer::from("002600"), key.encrypt(&buf).unwrap());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let key = Homophonic::new((0..52).map(|i| Char::from((i % 26) as u8)).collect()).unwrap();
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Meet at 9", alphanumeric).unwrap();
+
+        assert!(key.encrypt(&buf).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use crate::{Buffer, Char, PartialBuffer};
+use simple_error::SimpleError;
+use std::fmt;
+
+const LEN: usize = Char::MAX as usize;
+
+// The 25 disks of the US Army M-94, numbered from 1
+pub const M94_DISKS: [&str; 25] = [
+    "ABCEIGDJFVUYMHTQKZOLRXSPWN",
+    "ACDEHFIJKTLMOUVYGZNPQXRWSB",
+    "ADKOMJUBGEPHSCZINXFYQRTVWL",
+    "AEDCBIFGJHLKMRUOQVPTNWYXZS",
+    "AFNQUKDOPITJBRHCYSLWEMZVXG",
+    "AGPOCIXLURNDYZHWBJSQFKVMET",
+    "AHXJEZBNIKPVROGSYDULCFMQTW",
+    "AIHPJOBWKCVFZLQERYNSUMGTDX",
+    "AJDSKQOIVTZEFHGYUNLPMBXWCR",
+    "AKELBDFJGHONMTPRQSVZUXYWIC",
+    "ALTMSXVQPNOHUWDIZYCGKRFBEJ",
+    "AMNFLHQGCUJTBYPZKXISRDVEWO",
+    "ANCJILDHBMKGXUZTSWQYVORPFE",
+    "AODWPKJVIUQHZCTXBLEGNYRSMF",
+    "APBVHIYKSGUENTCXOWFQDRLJZM",
+    "AQJNUBTGIMWZRVLXCSHDEOKFPY",
+    "ARMYOFTHEUSZJXDPCWGQIBKLNV",
+    "ASDMCNEQBOZPLGVJRKYTFUIWXH",
+    "ATOJYLFXNGWHVCMIRBSEKUPDZQ",
+    "AUTRZXQLYIOVBPESNHJWMDGFCK",
+    "AVNKHRGOXEYBFSJMUDQCLZWTIP",
+    "AWVSFDLIEBHKNRJQZGMXPUCOTY",
+    "AXKWREVDTUFOYHMLSIQNJCPGBZ",
+    "AYJPXMVKBQWUGLOSTECHNZFRID",
+    "AZDNBUHYFWJLVGRCQMPSOEXTKI",
+];
+
+// A wheel cipher such as Jefferson's or the M-94. Every disk is threaded onto the axle in the
+// order of the key, and each block of text, one letter per disk, is set along a row. The
+// ciphertext is read from the row the generatrix offset further round.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jefferson {
+    disks: Vec<[Char; LEN]>,
+    order: Vec<usize>,
+    offset: u8,
+}
+
+pub(crate) fn parse_disk(disk: &str) -> Result<[Char; LEN], SimpleError> {
+    let mut out = [Char::from(0); LEN];
+    let mut seen = [false; LEN];
+    let mut n = 0;
+
+    for c in disk.chars() {
+        if !c.is_ascii_alphabetic() || n == LEN {
+            return Err(SimpleError::new(format!("invalid disk '{}'", disk)));
+        }
+
+        let c = Char::from(c);
+        if seen[usize::from(u8::from(c))] {
+            return Err(SimpleError::new(format!(
+                "repeated letter in disk '{}'",
+                disk
+            )));
+        }
+
+        seen[usize::from(u8::from(c))] = true;
+        out[n] = c;
+        n += 1;
+    }
+
+    if n < LEN {
+        return Err(SimpleError::new(format!("disk '{}' is too short", disk)));
+    }
+
+    Ok(out)
+}
+
+impl Jefferson {
+    // The disk order numbers disks from 1, as they were stamped, and must use each disk once
+    pub fn new<T: AsRef<str>>(
+        disks: &[T],
+        order: &[usize],
+        offset: u8,
+    ) -> Result<Self, SimpleError> {
+        let disks = disks
+            .iter()
+            .map(|d| parse_disk(d.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut used = vec![false; disks.len()];
+        for &d in order {
+            if d == 0 || d > disks.len() || used[d - 1] {
+                break;
+            }
+
+            used[d - 1] = true;
+        }
+
+        if order.len() != disks.len() || used.contains(&false) {
+            return Err(SimpleError::new(
+                "disk order must use each disk exactly once",
+            ));
+        }
+
+        Ok(Self {
+            disks,
+            order: order.iter().map(|d| d - 1).collect(),
+            offset: offset % Char::MAX,
+        })
+    }
+
+    pub fn m94(order: &[usize], offset: u8) -> Result<Self, SimpleError> {
+        Self::new(&M94_DISKS, order, offset)
+    }
+
+    // Takes the disks in an order counted from 0, as found when cracking
+    pub(crate) fn from_order(disks: Vec<[Char; LEN]>, order: Vec<usize>, offset: u8) -> Self {
+        Self {
+            disks,
+            order,
+            offset,
+        }
+    }
+
+    // The disk order, numbered from 1
+    pub fn order(&self) -> Vec<usize> {
+        self.order.iter().map(|d| d + 1).collect()
+    }
+
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
+    fn turn(&self, mut buf: PartialBuffer, offset: u8) -> PartialBuffer {
+        for (i, c) in (&mut buf).into_iter().enumerate() {
+            let disk = &self.disks[self.order[i % self.order.len()]];
+            let pos = disk.iter().position(|d| d == c).unwrap();
+            *c = disk[(pos + usize::from(offset)) % LEN];
+        }
+
+        buf
+    }
+}
+
+impl PartialEncrypt for Jefferson {
+    fn encrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+        Ok(self.turn(buf, self.offset))
+    }
+}
+
+impl PartialDecrypt for Jefferson {
+    fn decrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+        Ok(self.turn(buf, (Char::MAX - self.offset) % Char::MAX))
+    }
+}
+
+derive_encrypt_decrypt!(Jefferson, SimpleError);
+
+impl fmt::Display for Jefferson {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let order = self
+            .order()
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+
+        write!(f, "{} +{}", order.join(","), self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alphabet;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut jefferson = Jefferson::new(
+            &["ABCDEFGHIJKLMNOPQRSTUVWXYZ", "ZYXWVUTSRQPONMLKJIHGFEDCBA"],
+            &[2, 1],
+            1,
+        )
+        .unwrap();
+        let buf = Buffer::from("Hello world!");
+
+        let buf = jefferson.encrypt(buf).unwrap();
+        assert_eq!("Gfkmn xnske!", buf.to_string());
+
+        let buf = jefferson.decrypt(buf).unwrap();
+        assert_eq!("Hello world!", buf.to_string());
+    }
+
+    #[test]
+    fn test_m94() {
+        let order = (1..=25).rev().collect::<Vec<_>>();
+        let mut m94 = Jefferson::m94(&order, 7).unwrap();
+        let buf = Buffer::from("The quick brown fox jumps over the lazy dog");
+
+        let ciphertext = m94.encrypt(buf.clone()).unwrap();
+        assert_ne!(buf.to_string(), ciphertext.to_string());
+        assert_eq!(
+            buf.to_string(),
+            m94.decrypt(ciphertext).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Jefferson::m94(&[1, 2, 3], 1).is_err());
+        assert!(Jefferson::new(&["ABC"], &[1], 1).is_err());
+        assert!(Jefferson::new(&["ABCDEFGHIJKLMNOPQRSTUVWXYA"], &[1], 1).is_err());
+        assert!(Jefferson::new(&["ABCDEFGHIJKLMNOPQRSTUVWXYZ"], &[2], 1).is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Hello 2 world!", alphanumeric).unwrap();
+
+        let mut m94 = Jefferson::m94(&(1..=25).collect::<Vec<_>>(), 7).unwrap();
+        assert!(m94.encrypt_partial(buf.clone().into()).is_err());
+        assert!(m94.decrypt_partial(buf.into()).is_err());
+    }
+}
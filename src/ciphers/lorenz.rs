@@ -0,0 +1,276 @@
+use crate::baudot::{impulse, BaudotBuffer, IMPULSES};
+use rand::Rng;
+use simple_error::SimpleError;
+use std::fmt;
+
+pub const NUM_WHEELS: usize = 12;
+
+// The chi wheels, then the psi wheels, then the two motor wheels
+pub const WHEEL_SIZES: [usize; NUM_WHEELS] = [41, 31, 29, 26, 23, 43, 47, 51, 53, 59, 61, 37];
+
+pub const CHI: usize = 0;
+pub const PSI: usize = 5;
+pub const MU61: usize = 10;
+pub const MU37: usize = 11;
+
+// What may hold the psi wheels still alongside the motor wheels, on the later models
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limitation {
+    None,
+    // Chi 2 one back
+    Chi2,
+    // Chi 2 one back added to psi 1 two back
+    Chi2Psi1,
+    // Chi 2 one back added to the fifth impulse of the plaintext two back
+    Chi2P5,
+}
+
+// The Lorenz SZ40/42 teleprinter cipher attachment, or Tunny. Each character is added to the
+// chi wheels, which step every time, and to the psi wheels, which step together only when the
+// motor lets them. Mu 61 steps every time and mu 37 when mu 61 shows a cross. The psi wheels
+// move unless mu 37 shows a dot and the limitation a cross, so without a limitation they follow
+// mu 37. Adding is exclusive or, so encrypting and decrypting are the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lorenz {
+    pins: Vec<Vec<bool>>,
+    positions: [usize; NUM_WHEELS],
+    limitation: Limitation,
+}
+
+fn parse_pins(pattern: &str) -> Result<Vec<bool>, SimpleError> {
+    pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            'x' | 'X' | '1' => Ok(true),
+            '.' | '0' | '•' => Ok(false),
+            _ => Err(SimpleError::new(format!("invalid pin '{}'", c))),
+        })
+        .collect()
+}
+
+impl Lorenz {
+    // Takes the pin pattern of each wheel, in the order of `WHEEL_SIZES`, written with x for a
+    // cross and . for a dot. Every wheel starts at position 0.
+    pub fn new<T: AsRef<str>>(patterns: &[T]) -> Result<Self, SimpleError> {
+        if patterns.len() != NUM_WHEELS {
+            return Err(SimpleError::new(format!(
+                "expected {} wheel patterns",
+                NUM_WHEELS
+            )));
+        }
+
+        let pins = patterns
+            .iter()
+            .map(|p| parse_pins(p.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (w, p) in pins.iter().enumerate() {
+            if p.len() != WHEEL_SIZES[w] {
+                return Err(SimpleError::new(format!(
+                    "wheel {} needs {} pins",
+                    w + 1,
+                    WHEEL_SIZES[w]
+                )));
+            }
+        }
+
+        Ok(Self {
+            pins,
+            positions: [0; NUM_WHEELS],
+            limitation: Limitation::None,
+        })
+    }
+
+    pub fn rand_key<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self {
+            pins: WHEEL_SIZES
+                .iter()
+                .map(|&size| (0..size).map(|_| rng.gen()).collect())
+                .collect(),
+            positions: [0; NUM_WHEELS],
+            limitation: Limitation::None,
+        }
+    }
+
+    pub fn with_positions(mut self, positions: [usize; NUM_WHEELS]) -> Result<Self, SimpleError> {
+        for (w, &p) in positions.iter().enumerate() {
+            if p >= WHEEL_SIZES[w] {
+                return Err(SimpleError::new(format!(
+                    "position {} is off wheel {}",
+                    p,
+                    w + 1
+                )));
+            }
+        }
+
+        self.positions = positions;
+        Ok(self)
+    }
+
+    pub fn with_limitation(mut self, limitation: Limitation) -> Self {
+        self.limitation = limitation;
+        self
+    }
+
+    pub fn pins(&self, wheel: usize) -> &[bool] {
+        &self.pins[wheel]
+    }
+
+    pub fn positions(&self) -> [usize; NUM_WHEELS] {
+        self.positions
+    }
+
+    pub fn limitation(&self) -> Limitation {
+        self.limitation
+    }
+
+    fn run(&self, text: &BaudotBuffer, decrypting: bool) -> BaudotBuffer {
+        let mut pos = self.positions;
+        let mut plain5 = [false; 2];
+        let mut out = BaudotBuffer::default();
+
+        let pin = |pos: &[usize; NUM_WHEELS], w: usize, back: usize| {
+            let size = WHEEL_SIZES[w];
+            self.pins[w][(pos[w] + size - back) % size]
+        };
+
+        for &c in text {
+            let mut key = 0;
+            for i in 0..IMPULSES {
+                key = (key << 1) | u8::from(pin(&pos, CHI + i, 0) ^ pin(&pos, PSI + i, 0));
+            }
+
+            let p = if decrypting { c ^ key } else { c };
+            out.push(c ^ key);
+
+            let chi2 = pin(&pos, CHI + 1, 1);
+            let lim = match self.limitation {
+                Limitation::None => true,
+                Limitation::Chi2 => chi2,
+                Limitation::Chi2Psi1 => chi2 ^ pin(&pos, PSI, 2),
+                Limitation::Chi2P5 => chi2 ^ plain5[0],
+            };
+            let motor = pin(&pos, MU37, 0) || !lim;
+            plain5 = [plain5[1], impulse(p, IMPULSES - 1)];
+
+            for w in CHI..CHI + IMPULSES {
+                pos[w] = (pos[w] + 1) % WHEEL_SIZES[w];
+            }
+
+            if motor {
+                for w in PSI..PSI + IMPULSES {
+                    pos[w] = (pos[w] + 1) % WHEEL_SIZES[w];
+                }
+            }
+
+            if pin(&pos, MU61, 0) {
+                pos[MU37] = (pos[MU37] + 1) % WHEEL_SIZES[MU37];
+            }
+
+            pos[MU61] = (pos[MU61] + 1) % WHEEL_SIZES[MU61];
+        }
+
+        out
+    }
+
+    pub fn encrypt(&self, text: &BaudotBuffer) -> BaudotBuffer {
+        self.run(text, false)
+    }
+
+    pub fn decrypt(&self, text: &BaudotBuffer) -> BaudotBuffer {
+        self.run(text, true)
+    }
+}
+
+// The start positions, counted from 1 as on the machine
+impl fmt::Display for Lorenz {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let positions = self
+            .positions
+            .iter()
+            .map(|p| format!("{:02}", p + 1))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", positions.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Buffer;
+
+    const PLAINTEXT: &str = "Come at once to the northern ridge";
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let limitations = [
+            Limitation::None,
+            Limitation::Chi2,
+            Limitation::Chi2Psi1,
+            Limitation::Chi2P5,
+        ];
+
+        for &limitation in &limitations {
+            let key = Lorenz::rand_key(&mut rand::thread_rng())
+                .with_positions([3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8])
+                .unwrap()
+                .with_limitation(limitation);
+            let plaintext = BaudotBuffer::encode(&Buffer::from(PLAINTEXT)).unwrap();
+
+            let ciphertext = key.encrypt(&plaintext);
+            assert_ne!(plaintext, ciphertext);
+            assert_eq!(plaintext, key.decrypt(&ciphertext));
+        }
+    }
+
+    #[test]
+    fn test_stepping() {
+        // With crosses on every chi pin and dots elsewhere, the chi wheels invert every
+        // character and the psi wheels never move
+        let patterns = WHEEL_SIZES
+            .iter()
+            .enumerate()
+            .map(|(w, &size)| if w < PSI { "x" } else { "." }.repeat(size))
+            .collect::<Vec<_>>();
+        let key = Lorenz::new(&patterns).unwrap();
+
+        let ciphertext = key.encrypt(&BaudotBuffer::from("/T3O9"));
+        assert_eq!("8KQU5", ciphertext.to_string());
+
+        // A single cross on psi 1 only shows once the motor moves the psi wheels on to it
+        let mut patterns = patterns;
+        patterns[PSI] = format!("{}x", ".".repeat(42));
+        patterns[MU37] = "x".repeat(37);
+        let key = Lorenz::new(&patterns)
+            .unwrap()
+            .with_positions([0, 0, 0, 0, 0, 41, 0, 0, 0, 0, 0, 0])
+            .unwrap();
+
+        let ciphertext = key.encrypt(&BaudotBuffer::from("///"));
+        assert_eq!("8V8", ciphertext.to_string());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Lorenz::new(&["x"]).is_err());
+
+        let mut patterns = WHEEL_SIZES
+            .iter()
+            .map(|&s| "x".repeat(s))
+            .collect::<Vec<_>>();
+        assert!(Lorenz::new(&patterns).is_ok());
+
+        patterns[3].push('x');
+        assert!(Lorenz::new(&patterns).is_err());
+
+        patterns[3] = "a".repeat(26);
+        assert!(Lorenz::new(&patterns).is_err());
+
+        let key = Lorenz::rand_key(&mut rand::thread_rng());
+        assert!(key
+            .with_positions([41, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+            .is_err());
+    }
+}
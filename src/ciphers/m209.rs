@@ -0,0 +1,318 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use crate::{Buffer, Char, PartialBuffer};
+use rand::Rng;
+use simple_error::SimpleError;
+use std::fmt;
+
+pub const NUM_WHEELS: usize = 6;
+pub const NUM_BARS: usize = 27;
+pub const WHEEL_SIZES: [usize; NUM_WHEELS] = [26, 25, 23, 21, 19, 17];
+
+const WHEEL_LETTERS: [&str; NUM_WHEELS] = [
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "ABCDEFGHIJKLMNOPQRSTUVXYZ",
+    "ABCDEFGHIJKLMNOPQRSTUVX",
+    "ABCDEFGHIJKLMNOPQRSTU",
+    "ABCDEFGHIJKLMNOPQRS",
+    "ABCDEFGHIJKLMNOPQ",
+];
+
+// The pins that act on the drum sit this far round each wheel from the letter in the window
+const PIN_OFFSETS: [usize; NUM_WHEELS] = [15, 14, 13, 12, 11, 10];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct M209 {
+    // Whether each pin is active, indexed by wheel letter
+    pins: [Vec<bool>; NUM_WHEELS],
+    // The wheels (1 to 6) that each bar's two lugs sit against, or 0 for an unused lug
+    lugs: [[u8; 2]; NUM_BARS],
+    // Indices into the wheel letters shown in the window at the start of each message
+    positions: [usize; NUM_WHEELS],
+}
+
+impl M209 {
+    // Pins are given as the letters of the active pins on each wheel, lugs in the usual "1-0",
+    // "2-5" notation and positions as six letters
+    pub fn new(pins: &[&str], lugs: &[&str], positions: &str) -> Result<Self, SimpleError> {
+        if pins.len() != NUM_WHEELS {
+            return Err(SimpleError::new("pins must be given for all six wheels"));
+        }
+
+        let mut out = Self::default();
+
+        for (w, active) in pins.iter().enumerate() {
+            for c in active.chars() {
+                let i = wheel_index(w, c)?;
+                out.pins[w][i] = true;
+            }
+        }
+
+        if lugs.len() > NUM_BARS {
+            return Err(SimpleError::new("the drum only has 27 bars"));
+        }
+
+        for (bar, spec) in lugs.iter().enumerate() {
+            let wheels = spec
+                .split('-')
+                .map(|w| match w.trim().parse::<u8>() {
+                    Ok(w) if w as usize <= NUM_WHEELS => Ok(w),
+                    _ => Err(SimpleError::new(format!("invalid lug setting '{}'", spec))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match wheels[..] {
+                [a, b] if a == 0 || a != b => out.lugs[bar] = [a, b],
+                _ => return Err(SimpleError::new(format!("invalid lug setting '{}'", spec))),
+            }
+        }
+
+        let positions = positions.chars().collect::<Vec<_>>();
+        if positions.len() != NUM_WHEELS {
+            return Err(SimpleError::new(
+                "positions must be given for all six wheels",
+            ));
+        }
+
+        for (w, &c) in positions.iter().enumerate() {
+            out.positions[w] = wheel_index(w, c)?;
+        }
+
+        Ok(out)
+    }
+
+    // Random pins (roughly half active) and one lug per bar, with a few bars given a second
+    pub fn rand_key<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut out = Self::default();
+
+        for (w, pins) in out.pins.iter_mut().enumerate() {
+            *pins = (0..WHEEL_SIZES[w]).map(|_| rng.gen()).collect();
+        }
+
+        for bar in out.lugs.iter_mut() {
+            let a = rng.gen_range(1, NUM_WHEELS as u8 + 1);
+            let b = rng.gen_range(1, NUM_WHEELS as u8 + 1);
+
+            *bar = if a != b && rng.gen::<f64>() < 0.2 {
+                [a, b]
+            } else {
+                [0, a]
+            };
+        }
+
+        out
+    }
+
+    pub fn pin(&self, wheel: usize, i: usize) -> bool {
+        self.pins[wheel][i]
+    }
+
+    pub fn toggle_pin(&mut self, wheel: usize, i: usize) {
+        self.pins[wheel][i] = !self.pins[wheel][i];
+    }
+
+    pub fn lugs(&self) -> &[[u8; 2]; NUM_BARS] {
+        &self.lugs
+    }
+
+    pub fn set_lugs(&mut self, bar: usize, lugs: [u8; 2]) {
+        self.lugs[bar] = lugs;
+    }
+
+    pub fn positions(&self) -> [usize; NUM_WHEELS] {
+        self.positions
+    }
+
+    // The shift for each combination of active wheels (bit n set for wheel n + 1)
+    pub fn drum(&self) -> [u8; 1 << NUM_WHEELS] {
+        let mut out = [0; 1 << NUM_WHEELS];
+
+        for (mask, shift) in out.iter_mut().enumerate() {
+            *shift = self
+                .lugs
+                .iter()
+                .filter(|bar| bar.iter().any(|&w| w > 0 && mask & (1 << (w - 1)) != 0))
+                .count() as u8;
+        }
+
+        out
+    }
+
+    // The letters of a message of length `len` at which pin `i` of a wheel acts on the drum
+    pub fn pin_letters(&self, wheel: usize, i: usize, len: usize) -> impl Iterator<Item = usize> {
+        let size = WHEEL_SIZES[wheel];
+        let first = (i + 2 * size - self.positions[wheel] - PIN_OFFSETS[wheel]) % size;
+
+        (first..len).step_by(size)
+    }
+
+    // Which wheels have an active pin against the drum for the nth letter of a message
+    pub fn active_wheels(&self, n: usize) -> usize {
+        (0..NUM_WHEELS)
+            .filter(|&w| {
+                let i = (self.positions[w] + n + PIN_OFFSETS[w]) % WHEEL_SIZES[w];
+                self.pins[w][i]
+            })
+            .fold(0, |acc, w| acc | (1 << w))
+    }
+}
+
+fn wheel_index(wheel: usize, c: char) -> Result<usize, SimpleError> {
+    WHEEL_LETTERS[wheel]
+        .find(c.to_ascii_uppercase())
+        .ok_or_else(|| SimpleError::new(format!("wheel {} has no letter {}", wheel + 1, c)))
+}
+
+impl Default for M209 {
+    // No active pins and no lugs, which leaves only the reversal of the alphabet
+    fn default() -> Self {
+        Self {
+            pins: [
+                vec![false; WHEEL_SIZES[0]],
+                vec![false; WHEEL_SIZES[1]],
+                vec![false; WHEEL_SIZES[2]],
+                vec![false; WHEEL_SIZES[3]],
+                vec![false; WHEEL_SIZES[4]],
+                vec![false; WHEEL_SIZES[5]],
+            ],
+            lugs: [[0; 2]; NUM_BARS],
+            positions: [0; NUM_WHEELS],
+        }
+    }
+}
+
+impl PartialEncrypt for M209 {
+    // Beaufort style, c = shift - p - 1, which makes the machine reciprocal
+    fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        let drum = self.drum();
+
+        for (n, c) in (&mut buf).into_iter().enumerate() {
+            let shift = drum[self.active_wheels(n)];
+            *c = Char::from((Char::MAX * 2 - 1 + shift - u8::from(*c)) % Char::MAX);
+        }
+
+        Ok(buf)
+    }
+}
+
+impl PartialDecrypt for M209 {
+    fn decrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        self.encrypt_partial(buf)
+    }
+}
+
+derive_encrypt_decrypt!(M209, SimpleError);
+
+impl fmt::Display for M209 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (w, pins) in self.pins.iter().enumerate() {
+            let active = WHEEL_LETTERS[w]
+                .chars()
+                .zip(pins)
+                .filter(|&(_, &p)| p)
+                .map(|(c, _)| c)
+                .collect::<String>();
+
+            writeln!(f, "Wheel {}: {}", w + 1, active)?;
+        }
+
+        let lugs = self
+            .lugs
+            .iter()
+            .map(|bar| format!("{}-{}", bar[0], bar[1]))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(f, "Lugs: {}", lugs)?;
+
+        let positions = (0..NUM_WHEELS)
+            .map(|w| WHEEL_LETTERS[w].as_bytes()[self.positions[w]] as char)
+            .collect::<String>();
+        write!(f, "Positions: {}", positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> M209 {
+        M209::new(
+            &[
+                "ABDHIKMNSTVW",
+                "ADEGJKLORSUX",
+                "ABGHJLMNRSTUX",
+                "CEFHIMNPSTU",
+                "BDEFHIMNPS",
+                "ABDHKNOQ",
+            ],
+            &[
+                "3-6", "0-6", "1-6", "1-5", "4-5", "0-4", "0-4", "0-4", "0-4", "2-0", "2-0", "2-0",
+                "2-0", "2-0", "2-0", "2-0", "2-0", "2-0", "2-0", "2-5", "2-5", "0-5", "0-5", "0-5",
+                "0-5", "0-5", "0-5",
+            ],
+            "AAAAAA",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut m209 = key();
+        let plaintext = "Attack at dawn along the northern ridge";
+
+        let buf = m209.encrypt(Buffer::from(plaintext)).unwrap();
+        assert_ne!(plaintext, buf.to_string());
+
+        let buf = m209.decrypt(buf).unwrap();
+        assert_eq!(plaintext, buf.to_string());
+    }
+
+    #[test]
+    fn test_manual() {
+        // The check in the M-209 technical manual: with its test key and the wheels at AAAAAA,
+        // 26 As encipher to this
+        let buf = key()
+            .encrypt(Buffer::from("AAAAAAAAAAAAAAAAAAAAAAAAAA"))
+            .unwrap();
+        assert_eq!("TNJUWAUQTKCZKNUTOTBCWARMIO", buf.to_string());
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut m209 = M209::default();
+        // With nothing set the shift is always 0, so A becomes Z and Z becomes A
+        let buf = m209.encrypt(Buffer::from("AZM")).unwrap();
+        assert_eq!("ZAN", buf.to_string());
+
+        // Every pin on the first wheel active, and three bars against it
+        let mut m209 = M209::new(
+            &["ABCDEFGHIJKLMNOPQRSTUVWXYZ", "", "", "", "", ""],
+            &["1-0", "0-1", "1-2"],
+            "AAAAAA",
+        )
+        .unwrap();
+        assert_eq!(3, m209.drum()[1]);
+        assert_eq!(1, m209.drum()[2]);
+
+        let buf = m209.encrypt(Buffer::from("AZM")).unwrap();
+        assert_eq!("CDQ", buf.to_string());
+    }
+
+    #[test]
+    fn test_pin_letters() {
+        let m209 = M209::new(&["E", "", "", "", "", ""], &[], "CAAAAA").unwrap();
+
+        let letters = m209.pin_letters(0, 4, 100).collect::<Vec<_>>();
+        assert_eq!(vec![13, 39, 65, 91], letters);
+        assert!((0..100).all(|n| (m209.active_wheels(n) == 1) == letters.contains(&n)));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(M209::new(&["W", "W", "", "", "", ""], &[], "AAAAAA").is_err());
+        assert!(M209::new(&["", "", "", "", "", ""], &["7-0"], "AAAAAA").is_err());
+        assert!(M209::new(&["", "", "", "", "", ""], &["3-3"], "AAAAAA").is_err());
+        assert!(M209::new(&["", "", "", "", "", ""], &[], "AAAAAZ").is_err());
+    }
+}
@@ -20,6 +20,16 @@ macro_rules! derive_encrypt_decrypt {
     };
 }
 
+// Ciphers written for the 26 letters refuse buffers in other alphabets. Those whose arithmetic
+// wraps round at the size of the alphabet, or that only move letters about, leave it out.
+macro_rules! check_alphabet {
+    ($buf:expr, $err:ident) => {
+        if !$crate::CharStream::alphabet($buf).is_english() {
+            return Err($err::new("cipher needs the English alphabet"));
+        }
+    };
+}
+
 mod caesar;
 pub use self::caesar::Caesar;
 
@@ -29,12 +39,72 @@ pub use self::affine::Affine;
 mod vigenere;
 pub use self::vigenere::Vigenere;
 
+mod gronsfeld;
+pub use self::gronsfeld::Gronsfeld;
+
+mod progressive;
+pub use self::progressive::ProgressiveKey;
+
 mod transposition;
 pub use self::transposition::Transposition;
 
+mod cadenus;
+pub use self::cadenus::Cadenus;
+
 mod substitution;
 pub use self::substitution::Substitution;
 
+mod checkerboard;
+pub use self::checkerboard::StraddlingCheckerboard;
+
+mod vic;
+pub use self::vic::Vic;
+
+pub mod homophonic;
+pub use self::homophonic::Homophonic;
+
+pub mod book;
+pub use self::book::Book;
+
+pub mod nomenclator;
+pub use self::nomenclator::Nomenclator;
+
+pub mod baconian;
+pub use self::baconian::Baconian;
+
+mod fractionated_morse;
+pub use self::fractionated_morse::FractionatedMorse;
+
+mod morbit;
+pub use self::morbit::Morbit;
+
+mod pollux;
+pub use self::pollux::Pollux;
+
+pub mod enigma;
+pub use self::enigma::Enigma;
+
+pub mod m209;
+pub use self::m209::M209;
+
+pub mod lorenz;
+pub use self::lorenz::Lorenz;
+
+mod chaocipher;
+pub use self::chaocipher::Chaocipher;
+
+mod solitaire;
+pub use self::solitaire::Solitaire;
+
+pub mod quagmire;
+pub use self::quagmire::Quagmire;
+
+pub mod jefferson;
+pub use self::jefferson::Jefferson;
+
+mod porta;
+pub use self::porta::Porta;
+
 pub trait Encrypt {
     type Error: std::error::Error;
 
@@ -54,3 +124,10 @@ pub trait PartialEncrypt: Encrypt {
 pub trait PartialDecrypt: Decrypt {
     fn decrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error>;
 }
+
+// Ciphers that can work through a long text a piece at a time, because what they do to a letter
+// depends on nothing but how many letters came before it
+pub trait Resumable {
+    // Moves the key on past `n` letters, ready for the next piece
+    fn skip(&mut self, n: usize);
+}
@@ -0,0 +1,109 @@
+use crate::morse::{MorseBuffer, Symbol};
+use crate::{Buffer, DigitBuffer};
+use simple_error::SimpleError;
+
+const SYMBOLS: [Symbol; 3] = [Symbol::Dot, Symbol::Dash, Symbol::Separator];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Morbit {
+    // The digit for each pair of symbols, in the order .., .-, .x, -., --, -x, x., x-, xx
+    digits: [u8; 9],
+}
+
+impl Morbit {
+    // Takes either a 9 letter keyword, which is numbered alphabetically, or a permutation of 1 to 9
+    pub fn new<T: AsRef<str>>(key: T) -> Result<Self, SimpleError> {
+        let key = key
+            .as_ref()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<_>>();
+
+        if key.len() != 9 {
+            return Err(SimpleError::new("morbit key must be 9 characters long"));
+        }
+
+        let mut digits = [0; 9];
+
+        if key.iter().all(|c| c.is_ascii_digit()) {
+            for (d, c) in digits.iter_mut().zip(&key) {
+                *d = c.to_digit(10).unwrap() as u8;
+            }
+
+            let mut sorted = digits;
+            sorted.sort_unstable();
+            if sorted != [1, 2, 3, 4, 5, 6, 7, 8, 9] {
+                return Err(SimpleError::new(
+                    "morbit key must be a permutation of 1 to 9",
+                ));
+            }
+        } else if key.iter().all(|c| c.is_ascii_alphabetic()) {
+            let mut order = (0..9).collect::<Vec<_>>();
+            order.sort_by_key(|&i| key[i].to_ascii_uppercase()); // Stable, so ties go left to right
+
+            for (rank, &i) in order.iter().enumerate() {
+                digits[i] = rank as u8 + 1;
+            }
+        } else {
+            return Err(SimpleError::new("morbit key must be letters or digits"));
+        }
+
+        Ok(Self { digits })
+    }
+
+    pub fn encrypt(&self, buf: &Buffer) -> Result<DigitBuffer, SimpleError> {
+        let mut morse = MorseBuffer::encode(buf)?;
+        morse.pad_to_multiple(2);
+
+        Ok(DigitBuffer::new(
+            morse
+                .as_slice()
+                .chunks(2)
+                .map(|pair| {
+                    let i = SYMBOLS.iter().position(|s| *s == pair[0]).unwrap();
+                    let j = SYMBOLS.iter().position(|s| *s == pair[1]).unwrap();
+                    self.digits[i * 3 + j]
+                })
+                .collect(),
+        ))
+    }
+
+    pub fn decrypt(&self, digits: &DigitBuffer) -> Result<Buffer, SimpleError> {
+        let mut morse = MorseBuffer::default();
+
+        for &d in digits {
+            let pair = self
+                .digits
+                .iter()
+                .position(|&x| x == d)
+                .ok_or_else(|| SimpleError::new("morbit ciphertext may not contain 0"))?;
+
+            morse.push(SYMBOLS[pair / 3]);
+            morse.push(SYMBOLS[pair % 3]);
+        }
+
+        morse.decode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let morbit = Morbit::new("WISECRACK").unwrap();
+        assert_eq!(Morbit::new("958427136").unwrap(), morbit);
+
+        let digits = morbit.encrypt(&Buffer::from("At")).unwrap();
+        assert_eq!(DigitBuffer::from("53"), digits);
+
+        let digits = morbit.encrypt(&Buffer::from("Once upon a time")).unwrap();
+        let buf = morbit.decrypt(&digits).unwrap();
+        assert_eq!("ONCE UPON A TIME", buf.to_string());
+
+        assert!(morbit.decrypt(&DigitBuffer::from("0")).is_err());
+        assert!(Morbit::new("12345678").is_err());
+        assert!(Morbit::new("123456788").is_err());
+    }
+}
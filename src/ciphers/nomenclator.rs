@@ -0,0 +1,219 @@
+use crate::{Buffer, Char};
+use simple_error::SimpleError;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// A nomenclator, the mixed code and cipher of early modern diplomacy. The codebook gives codes
+// for whole words, and for syllables and single letters to spell out anything else. Entries may
+// have several codes, which are used in turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nomenclator {
+    codes: HashMap<String, Vec<u32>>,
+    entries: HashMap<u32, String>,
+    longest: usize,
+}
+
+impl Nomenclator {
+    pub fn new<T: AsRef<str>>(codebook: &[(T, u32)]) -> Result<Self, SimpleError> {
+        let mut codes = HashMap::<String, Vec<u32>>::new();
+        let mut entries = HashMap::new();
+
+        for (entry, code) in codebook {
+            let entry = entry.as_ref().to_ascii_uppercase();
+
+            if entry.is_empty() || !entry.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(SimpleError::new(format!(
+                    "invalid codebook entry '{}'",
+                    entry
+                )));
+            }
+
+            if entries.insert(*code, entry.clone()).is_some() {
+                return Err(SimpleError::new(format!("code {} is used twice", code)));
+            }
+
+            codes.entry(entry).or_default().push(*code);
+        }
+
+        if codes.is_empty() {
+            return Err(SimpleError::new("codebook is empty"));
+        }
+
+        let longest = codes.keys().map(|e| e.len()).max().unwrap();
+        Ok(Self {
+            codes,
+            entries,
+            longest,
+        })
+    }
+
+    // Each word is coded by the longest entries that fit, from the start of the word
+    pub fn encrypt(&self, buf: &Buffer) -> Result<Vec<u32>, SimpleError> {
+        check_alphabet!(buf, SimpleError);
+
+        let text = buf.to_string().to_ascii_uppercase();
+        let mut used = HashMap::<&str, usize>::new();
+        let mut out = Vec::new();
+
+        for word in text.split(|c: char| !c.is_ascii_alphabetic()) {
+            let mut rest = word;
+
+            while !rest.is_empty() {
+                let found = (1..=self.longest.min(rest.len()))
+                    .rev()
+                    .find_map(|n| self.codes.get_key_value(&rest[..n]));
+
+                let (entry, codes) = match found {
+                    Some(found) => found,
+                    None => {
+                        let msg = format!("no codebook entry for '{}'", rest);
+                        return Err(SimpleError::new(msg));
+                    }
+                };
+
+                let count = used.entry(entry).or_insert(0);
+                out.push(codes[*count % codes.len()]);
+                *count += 1;
+
+                rest = &rest[entry.len()..];
+            }
+        }
+
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, codes: &[u32]) -> Result<Buffer, SimpleError> {
+        let mut original = String::new();
+
+        for code in codes {
+            let entry = self
+                .entries
+                .get(code)
+                .ok_or_else(|| SimpleError::new(format!("unknown code {}", code)))?;
+
+            original.push_str(entry);
+        }
+
+        let data = original.chars().map(Char::from).collect();
+        Ok(Buffer::new(data, Arc::new(original)))
+    }
+
+    // Decodes a list of codes split by spaces or commas
+    pub fn decode(&self, codes: &str) -> Result<Buffer, SimpleError> {
+        self.decrypt(&parse_codes(codes)?)
+    }
+}
+
+pub fn parse_codes(codes: &str) -> Result<Vec<u32>, SimpleError> {
+    codes
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse()
+                .map_err(|_| SimpleError::new(format!("invalid code '{}'", s)))
+        })
+        .collect()
+}
+
+pub fn format_codes(codes: &[u32]) -> String {
+    codes
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Reads a codebook with an entry on each line followed by its codes, such as "KING 101 102"
+impl FromStr for Nomenclator {
+    type Err = SimpleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut codebook = Vec::new();
+
+        for line in s.lines().filter(|l| !l.trim().is_empty()) {
+            let mut parts = line.split_whitespace();
+            let entry = parts.next().unwrap();
+            let codes = parse_codes(&parts.collect::<Vec<_>>().join(" "))?;
+
+            if codes.is_empty() {
+                return Err(SimpleError::new(format!("no codes for '{}'", entry)));
+            }
+
+            codebook.extend(codes.into_iter().map(|c| (entry, c)));
+        }
+
+        Self::new(&codebook)
+    }
+}
+
+impl fmt::Display for Nomenclator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut entries = self.codes.iter().collect::<Vec<_>>();
+        entries.sort();
+
+        for (entry, codes) in entries {
+            writeln!(f, "{} {}", entry, format_codes(codes))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Alphabet;
+
+    const CODEBOOK: &str = "KING 101 102\n\
+                            ARMY 103\n\
+                            TH 20\n\
+                            A 1\nB 2\nC 3\nD 4\nE 5 55\nF 6\nG 7\nH 8\nI 9\nJ 10\nK 11\nL 12\n\
+                            M 13\nN 14\nO 15\nP 16\nQ 17\nR 18\nS 19\nT 21\nU 22\nV 23\nW 24\n\
+                            X 25\nY 26\nZ 27";
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let nomenclator = CODEBOOK.parse::<Nomenclator>().unwrap();
+        let buf = Buffer::from("The king's army meets the king at Leith");
+
+        let codes = nomenclator.encrypt(&buf).unwrap();
+        assert_eq!(
+            "20 5 101 19 103 13 55 5 21 19 20 55 102 1 21 12 5 9 20",
+            format_codes(&codes)
+        );
+
+        let out = nomenclator.decode(&format_codes(&codes)).unwrap();
+        assert_eq!("THEKINGSARMYMEETSTHEKINGATLEITH", out.to_string());
+    }
+
+    #[test]
+    fn test_codebook() {
+        let nomenclator = Nomenclator::new(&[("yes", 1), ("no", 2), ("no", 3)]).unwrap();
+        assert_eq!("NO 2 3\nYES 1\n", nomenclator.to_string());
+        assert_eq!(nomenclator, nomenclator.to_string().parse().unwrap());
+
+        assert!(Nomenclator::new(&[("yes", 1), ("no", 1)]).is_err());
+        assert!(Nomenclator::new(&[("no way", 1)]).is_err());
+        assert!("YES".parse::<Nomenclator>().is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let nomenclator = Nomenclator::new(&[("yes", 1), ("no", 2)]).unwrap();
+
+        assert!(nomenclator.encrypt(&Buffer::from("maybe")).is_err());
+        assert!(nomenclator.decode("1 3").is_err());
+        assert!(nomenclator.decode("1 x").is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let nomenclator = Nomenclator::new(&[("yes", 1), ("no", 2)]).unwrap();
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("yes 2", alphanumeric).unwrap();
+
+        assert!(nomenclator.encrypt(&buf).is_err());
+    }
+}
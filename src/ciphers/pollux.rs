@@ -0,0 +1,89 @@
+use crate::morse::{MorseBuffer, Symbol};
+use crate::{Buffer, DigitBuffer};
+use simple_error::SimpleError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pollux {
+    // The Morse symbol that each digit stands for
+    symbols: [Symbol; 10],
+}
+
+impl Pollux {
+    // The key gives the symbol ('.', '-' or 'x') for each of the digits 0 to 9
+    pub fn new<T: AsRef<str>>(key: T) -> Result<Self, SimpleError> {
+        let key = key
+            .as_ref()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                Symbol::from_char(c)
+                    .ok_or_else(|| SimpleError::new("pollux key may only contain '.', '-' and 'x'"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if key.len() != 10 {
+            return Err(SimpleError::new("pollux key must be 10 symbols long"));
+        }
+
+        for s in &[Symbol::Dot, Symbol::Dash, Symbol::Separator] {
+            if !key.contains(s) {
+                return Err(SimpleError::new(
+                    "pollux key must contain every symbol at least once",
+                ));
+            }
+        }
+
+        let mut symbols = [Symbol::Dot; 10];
+        symbols.copy_from_slice(&key);
+
+        Ok(Self { symbols })
+    }
+
+    // The digits standing for each symbol are used in turn
+    pub fn encrypt(&self, buf: &Buffer) -> Result<DigitBuffer, SimpleError> {
+        let mut used = [0; 3];
+        let mut out = DigitBuffer::default();
+
+        for &symbol in &MorseBuffer::encode(buf)? {
+            let choices = (0..10)
+                .filter(|&d| self.symbols[d] == symbol)
+                .collect::<Vec<_>>();
+            let i = symbol as usize;
+
+            out.push(choices[used[i] % choices.len()] as u8);
+            used[i] += 1;
+        }
+
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, digits: &DigitBuffer) -> Result<Buffer, SimpleError> {
+        MorseBuffer::new(
+            digits
+                .into_iter()
+                .map(|&d| self.symbols[d as usize])
+                .collect(),
+        )
+        .decode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let pollux = Pollux::new("x.-..x-x-.").unwrap();
+
+        let digits = pollux.encrypt(&Buffer::from("At")).unwrap();
+        assert_eq!(DigitBuffer::from("1206"), digits);
+
+        let digits = pollux.encrypt(&Buffer::from("Luck helps")).unwrap();
+        let buf = pollux.decrypt(&digits).unwrap();
+        assert_eq!("LUCK HELPS", buf.to_string());
+
+        assert!(Pollux::new("..........").is_err());
+        assert!(Pollux::new("x.-").is_err());
+    }
+}
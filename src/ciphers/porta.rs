@@ -0,0 +1,195 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use crate::meta::HeuristicTarget;
+use crate::{Buffer, Char, PartialBuffer};
+use rand::Rng;
+use simple_error::SimpleError;
+use smallvec::SmallVec;
+use std::fmt;
+
+// Each row of the tableau swaps the first half of the alphabet with the second
+const HALF: u8 = Char::MAX / 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Porta {
+    // The row of the tableau for each letter of the key, where AB is 0 and YZ is 12
+    rows: SmallVec<[u8; 32]>,
+}
+
+impl Porta {
+    pub fn new<T: AsRef<str>>(key: T) -> Result<Self, SimpleError> {
+        let rows = key
+            .as_ref()
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| u8::from(Char::from(c)) / 2)
+            .collect::<SmallVec<[u8; 32]>>();
+
+        if rows.is_empty() {
+            return Err(SimpleError::new("key must contain at least one letter"));
+        }
+
+        Ok(Self { rows })
+    }
+
+    // One row of the tableau per column, each below 13
+    pub(crate) fn from_rows(rows: &[u8]) -> Self {
+        assert!(!rows.is_empty(), "rows was empty");
+        assert!(rows.iter().all(|&r| r < HALF), "row out of range");
+
+        Self {
+            rows: SmallVec::from(rows),
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.rows.len()
+    }
+
+    // The first letter of each key pair, which is enough to give back the rows
+    pub fn key(&self) -> String {
+        self.rows
+            .iter()
+            .map(|&r| char::from(Char::from(r * 2)))
+            .collect()
+    }
+
+    fn substitute(c: u8, row: u8) -> u8 {
+        if c < HALF {
+            HALF + (c + row) % HALF
+        } else {
+            (c - row) % HALF
+        }
+    }
+}
+
+impl PartialEncrypt for Porta {
+    fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        for (i, c) in (&mut buf).into_iter().enumerate() {
+            let row = self.rows[i % self.rows.len()];
+            *c = Char::from(Self::substitute(u8::from(*c), row));
+        }
+
+        Ok(buf)
+    }
+}
+
+// Every row of the tableau is its own inverse
+impl PartialDecrypt for Porta {
+    fn decrypt_partial(&mut self, buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        self.encrypt_partial(buf)
+    }
+}
+
+derive_encrypt_decrypt!(Porta, SimpleError);
+
+impl fmt::Display for Porta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+// The key param is the period. Tweaking moves a single column to another row. As each column is
+// enciphered with one of only 13 half-alphabets, `meta::porta::PortaSolve` can instead try every
+// row of every column directly.
+impl HeuristicTarget for Porta {
+    type KeyParam = usize;
+
+    fn rand_key<R: Rng + ?Sized>(param: Self::KeyParam, rng: &mut R) -> Self {
+        assert!(param > 0, "period was zero");
+
+        Self {
+            rows: (0..param).map(|_| rng.gen_range(0, HALF)).collect(),
+        }
+    }
+
+    fn tweak_key<R: Rng + ?Sized>(&self, _param: Self::KeyParam, rng: &mut R) -> Self {
+        let mut s = self.clone();
+        let i = rng.gen_range(0, s.rows.len());
+        s.rows[i] = (s.rows[i] + rng.gen_range(1, HALF)) % HALF;
+        s
+    }
+
+    // Counts through all 13^period keys, so only practical for short periods
+    fn next_key(key: Option<Self>, param: Self::KeyParam) -> Option<Self> {
+        let mut key = match key {
+            Some(k) => k,
+            None => {
+                return Some(Self {
+                    rows: SmallVec::from_elem(0, param),
+                })
+            }
+        };
+
+        for row in key.rows.iter_mut().rev() {
+            *row += 1;
+            if *row < HALF {
+                return Some(key);
+            }
+
+            *row = 0;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::hillclimb::HillClimb;
+    use crate::meta::Metaheuristic;
+    use crate::score::ScoreMethod;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut porta = Porta::new("FORTIFICATION").unwrap();
+        let buf = Buffer::from("Defend the east wall of the castle");
+
+        let buf = porta.encrypt(buf).unwrap();
+        assert_eq!("Synnjs cvr nrla hutu ku cvr yrlany", buf.to_string());
+
+        let buf = porta.decrypt(buf).unwrap();
+        assert_eq!("Defend the east wall of the castle", buf.to_string());
+    }
+
+    #[test]
+    fn test_key_pairs() {
+        // Both letters of a pair select the same row
+        assert_eq!(Porta::new("ACEG").unwrap(), Porta::new("BDFH").unwrap());
+        assert_eq!("ACEG", Porta::new("BDFH").unwrap().key());
+        assert!(Porta::new("").is_err());
+    }
+
+    #[test]
+    fn test_next_key() {
+        let mut keys = 0;
+        let mut cur_key = None;
+
+        while let Some(key) = Porta::next_key(cur_key, 2) {
+            cur_key = Some(key);
+            keys += 1;
+        }
+
+        assert_eq!(keys, 169);
+    }
+
+    #[test]
+    fn test_hillclimb() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                                 TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                                 EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT"
+            .into();
+        let mut key = Porta::new("PORTCULLIS").unwrap();
+        let ciphertext = key.encrypt(plaintext.clone()).unwrap();
+
+        let results = HillClimb::new(200, 5)
+            .crack_ciphertext::<Porta>(ciphertext, 10, ScoreMethod::Quadgrams, 1)
+            .unwrap();
+
+        assert_eq!(results[0].buf, plaintext);
+        assert_eq!(key, results[0].key);
+    }
+}
@@ -0,0 +1,129 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use crate::{Buffer, Char, PartialBuffer};
+use simple_error::SimpleError;
+use smallvec::SmallVec;
+use std::fmt;
+
+// A Vigenère cipher whose key moves on by the progression index each time it is used up, so the
+// second pass of a key KEY with progression 1 is LFZ, the third MGA and so on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressiveKey {
+    key: SmallVec<[u8; 32]>,
+    progression: u8,
+}
+
+impl ProgressiveKey {
+    pub fn new(key: &str, progression: u8) -> Result<Self, SimpleError> {
+        let key = key
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| u8::from(Char::from(c)))
+            .collect::<SmallVec<[u8; 32]>>();
+
+        if key.is_empty() {
+            return Err(SimpleError::new("key must contain at least one letter"));
+        }
+
+        Ok(Self {
+            key,
+            progression: progression % Char::MAX,
+        })
+    }
+
+    // Builds a key straight from its shifts, as found when cracking
+    pub(crate) fn from_shifts(key: &[u8], progression: u8) -> Self {
+        assert!(!key.is_empty(), "key was empty");
+
+        Self {
+            key: SmallVec::from(key),
+            progression,
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.key.len()
+    }
+
+    pub fn progression(&self) -> u8 {
+        self.progression
+    }
+
+    pub fn key(&self) -> String {
+        self.key
+            .iter()
+            .map(|&c| char::from(Char::from(c)))
+            .collect()
+    }
+
+    // The shift for the letter at index `i`
+    fn shift(&self, i: usize) -> u8 {
+        let pass = (i / self.key.len()) % Char::MAX as usize;
+        let progress = (pass * usize::from(self.progression)) % Char::MAX as usize;
+
+        (self.key[i % self.key.len()] + progress as u8) % Char::MAX
+    }
+}
+
+impl PartialEncrypt for ProgressiveKey {
+    fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        for (i, b) in (&mut buf).into_iter().enumerate() {
+            *b += self.shift(i);
+        }
+
+        Ok(buf)
+    }
+}
+
+impl PartialDecrypt for ProgressiveKey {
+    fn decrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        for (i, b) in (&mut buf).into_iter().enumerate() {
+            *b -= self.shift(i);
+        }
+
+        Ok(buf)
+    }
+}
+
+derive_encrypt_decrypt!(ProgressiveKey, SimpleError);
+
+impl fmt::Display for ProgressiveKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} +{}", self.key(), self.progression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphers::Vigenere;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut progressive = ProgressiveKey::new("KEY", 1).unwrap();
+        let buf = Buffer::from("Hello world!");
+
+        // The second pass uses LFZ, the third MGA and the last NHB
+        let buf = progressive.encrypt(buf).unwrap();
+        assert_eq!("Rijwt vaxlq!", buf.to_string());
+
+        let buf = progressive.decrypt(buf).unwrap();
+        assert_eq!("Hello world!", buf.to_string());
+    }
+
+    #[test]
+    fn test_no_progression() {
+        // Without a progression it is the plain Vigenère cipher
+        let buf = Buffer::from("Hello world!");
+        let progressive = ProgressiveKey::new("KEY", 0)
+            .unwrap()
+            .encrypt(buf.clone())
+            .unwrap();
+        let vigenere = Vigenere::new("KEY").encrypt(buf).unwrap();
+
+        assert_eq!(vigenere.to_string(), progressive.to_string());
+    }
+}
@@ -0,0 +1,260 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt, Substitution};
+use crate::{Buffer, Char, PartialBuffer};
+use simple_error::SimpleError;
+use smallvec::SmallVec;
+use std::fmt;
+
+const LEN: usize = Char::MAX as usize;
+
+// Which of the two alphabets are keyed, following the ACA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuagmireType {
+    // Keyed plain alphabet, straight cipher alphabet
+    I,
+    // Straight plain alphabet, keyed cipher alphabet
+    II,
+    // The same keyed alphabet for both, as in Kryptos
+    III,
+    // Differently keyed plain and cipher alphabets
+    IV,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quagmire {
+    kind: QuagmireType,
+    plain: [Char; LEN],
+    cipher: [Char; LEN],
+    // How far along the cipher alphabet is slid for each letter of the indicator
+    shifts: SmallVec<[u8; 32]>,
+}
+
+fn positions(alphabet: &[Char; LEN]) -> [u8; LEN] {
+    let mut out = [0; LEN];
+    for (i, &c) in alphabet.iter().enumerate() {
+        out[usize::from(u8::from(c))] = i as u8;
+    }
+
+    out
+}
+
+impl QuagmireType {
+    // The position in the plain alphabet that each indicator letter is set beneath. Quagmire I
+    // uses plaintext A, and the others the start of the plain alphabet, which for II is also A.
+    fn anchor(self, plain: &[Char; LEN]) -> u8 {
+        match self {
+            QuagmireType::I => positions(plain)[0],
+            _ => 0,
+        }
+    }
+}
+
+impl Quagmire {
+    // For types I to III, `keyword` keys whichever alphabets are keyed. Type IV takes a keyword
+    // for each alphabet.
+    pub fn new(kind: QuagmireType, keyword: &str, indicator: &str) -> Result<Self, SimpleError> {
+        if kind == QuagmireType::IV {
+            return Err(SimpleError::new("Quagmire IV needs two keywords"));
+        }
+
+        let keyed = Substitution::keyed_alphabet(keyword);
+        let straight = Substitution::keyed_alphabet("");
+
+        match kind {
+            QuagmireType::I => Self::from_alphabets(kind, keyed, straight, indicator),
+            QuagmireType::II => Self::from_alphabets(kind, straight, keyed, indicator),
+            _ => Self::from_alphabets(kind, keyed, keyed, indicator),
+        }
+    }
+
+    pub fn new_iv(
+        plain_keyword: &str,
+        cipher_keyword: &str,
+        indicator: &str,
+    ) -> Result<Self, SimpleError> {
+        Self::from_alphabets(
+            QuagmireType::IV,
+            Substitution::keyed_alphabet(plain_keyword),
+            Substitution::keyed_alphabet(cipher_keyword),
+            indicator,
+        )
+    }
+
+    fn from_alphabets(
+        kind: QuagmireType,
+        plain: [Char; LEN],
+        cipher: [Char; LEN],
+        indicator: &str,
+    ) -> Result<Self, SimpleError> {
+        let cipher_pos = positions(&cipher);
+        let anchor = kind.anchor(&plain);
+
+        let shifts = indicator
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| {
+                let pos = cipher_pos[usize::from(u8::from(Char::from(c)))];
+                (pos + Char::MAX - anchor) % Char::MAX
+            })
+            .collect::<SmallVec<[u8; 32]>>();
+
+        if shifts.is_empty() {
+            return Err(SimpleError::new(
+                "indicator must contain at least one letter",
+            ));
+        }
+
+        Ok(Self {
+            kind,
+            plain,
+            cipher,
+            shifts,
+        })
+    }
+
+    // Builds a key straight from its alphabets and the shift for each column, as found when
+    // cracking
+    pub(crate) fn from_shifts(
+        kind: QuagmireType,
+        plain: [Char; LEN],
+        cipher: [Char; LEN],
+        shifts: &[u8],
+    ) -> Self {
+        assert!(!shifts.is_empty(), "shifts was empty");
+
+        Self {
+            kind,
+            plain,
+            cipher,
+            shifts: SmallVec::from(shifts),
+        }
+    }
+
+    pub fn kind(&self) -> QuagmireType {
+        self.kind
+    }
+
+    pub fn period(&self) -> usize {
+        self.shifts.len()
+    }
+
+    pub fn indicator(&self) -> String {
+        let anchor = self.kind.anchor(&self.plain);
+
+        self.shifts
+            .iter()
+            .map(|&s| char::from(self.cipher[usize::from((s + anchor) % Char::MAX)]))
+            .collect()
+    }
+}
+
+impl PartialEncrypt for Quagmire {
+    fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        let plain_pos = positions(&self.plain);
+
+        for (i, c) in (&mut buf).into_iter().enumerate() {
+            let shift = self.shifts[i % self.shifts.len()];
+            let pos = plain_pos[usize::from(u8::from(*c))] + shift;
+            *c = self.cipher[usize::from(pos % Char::MAX)];
+        }
+
+        Ok(buf)
+    }
+}
+
+impl PartialDecrypt for Quagmire {
+    fn decrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        let cipher_pos = positions(&self.cipher);
+
+        for (i, c) in (&mut buf).into_iter().enumerate() {
+            let shift = self.shifts[i % self.shifts.len()];
+            let pos = cipher_pos[usize::from(u8::from(*c))] + Char::MAX - shift;
+            *c = self.plain[usize::from(pos % Char::MAX)];
+        }
+
+        Ok(buf)
+    }
+}
+
+derive_encrypt_decrypt!(Quagmire, SimpleError);
+
+impl fmt::Display for Quagmire {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letters =
+            |alphabet: &[Char; LEN]| alphabet.iter().map(|&c| char::from(c)).collect::<String>();
+
+        write!(
+            f,
+            "Quagmire {:?} {} {} {}",
+            self.kind,
+            letters(&self.plain),
+            letters(&self.cipher),
+            self.indicator()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kryptos() {
+        // The first section of Kryptos
+        let mut quagmire = Quagmire::new(QuagmireType::III, "KRYPTOS", "PALIMPSEST").unwrap();
+        let ciphertext =
+            Buffer::from("EMUFPHZLRFAXYUSDJKZLDKRNSHGNFIVJYQTQUXQBQVYUVLLTREVJYQTMKYRDMFD");
+
+        let buf = quagmire.decrypt(ciphertext.clone()).unwrap();
+        assert_eq!(
+            "BETWEENSUBTLESHADINGANDTHEABSENCEOFLIGHTLIESTHENUANCEOFIQLUSION",
+            buf.to_string()
+        );
+
+        let buf = quagmire.encrypt(buf).unwrap();
+        assert_eq!(ciphertext.to_string(), buf.to_string());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let plaintext = "Attack at dawn along the northern ridge";
+        let keys = vec![
+            Quagmire::new(QuagmireType::I, "SPRINGFEVER", "FLOWER").unwrap(),
+            Quagmire::new(QuagmireType::II, "SPRINGFEVER", "FLOWER").unwrap(),
+            Quagmire::new(QuagmireType::III, "SPRINGFEVER", "FLOWER").unwrap(),
+            Quagmire::new_iv("SPRINGFEVER", "PERCUSSION", "FLOWER").unwrap(),
+        ];
+
+        for mut key in keys {
+            assert_eq!("FLOWER", key.indicator());
+
+            let buf = key.encrypt(Buffer::from(plaintext)).unwrap();
+            assert_ne!(plaintext, buf.to_string());
+
+            let buf = key.decrypt(buf).unwrap();
+            assert_eq!(plaintext, buf.to_string());
+        }
+    }
+
+    #[test]
+    fn test_indicator_alignment() {
+        // Quagmire I sets the indicator under plaintext A, so that A enciphers to it
+        let mut quagmire = Quagmire::new(QuagmireType::I, "SPRINGFEVER", "FLOWER").unwrap();
+        let buf = quagmire.encrypt(Buffer::from("AAAAAA")).unwrap();
+        assert_eq!("FLOWER", buf.to_string());
+
+        // The others set it under the first letter of the plain alphabet
+        let mut quagmire = Quagmire::new(QuagmireType::III, "SPRINGFEVER", "FLOWER").unwrap();
+        let buf = quagmire.encrypt(Buffer::from("SSSSSS")).unwrap();
+        assert_eq!("FLOWER", buf.to_string());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Quagmire::new(QuagmireType::III, "KRYPTOS", "").is_err());
+        assert!(Quagmire::new(QuagmireType::IV, "KRYPTOS", "PALIMPSEST").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use crate::{Buffer, Char, PartialBuffer};
+use simple_error::SimpleError;
+use std::fmt;
+
+pub const DECK_SIZE: usize = 54;
+
+// The two jokers, numbered after the 52 cards in bridge order
+pub const JOKER_A: u8 = 53;
+pub const JOKER_B: u8 = 54;
+
+// Bruce Schneier's Solitaire, or Pontifex from Cryptonomicon. A shuffled deck of cards is
+// stepped to give a keystream, which is added to the plaintext. Every message starts from the
+// same keyed deck, so the key must never be reused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solitaire {
+    deck: Vec<u8>,
+}
+
+impl Solitaire {
+    // The deck in bridge order, clubs then diamonds, hearts and spades, followed by the jokers
+    pub fn unkeyed() -> Self {
+        Self {
+            deck: (1..=JOKER_B).collect(),
+        }
+    }
+
+    // Keys the deck from a passphrase. Each letter steps the deck and then makes a second count
+    // cut by its own value.
+    pub fn new(passphrase: &str) -> Self {
+        let mut out = Self::unkeyed();
+
+        for c in passphrase.chars().filter(|c| c.is_ascii_alphabetic()) {
+            out.step();
+            out.count_cut(u8::from(Char::from(c)) + 1);
+        }
+
+        out
+    }
+
+    // Takes the deck as card numbers from the top, with 53 and 54 for the A and B jokers
+    pub fn from_deck(deck: &[u8]) -> Result<Self, SimpleError> {
+        let mut seen = [false; DECK_SIZE];
+
+        for &card in deck {
+            if card == 0 || card > JOKER_B || seen[usize::from(card - 1)] {
+                return Err(SimpleError::new(
+                    "deck must hold each of the cards 1 to 54 once",
+                ));
+            }
+
+            seen[usize::from(card - 1)] = true;
+        }
+
+        if deck.len() != DECK_SIZE {
+            return Err(SimpleError::new(
+                "deck must hold each of the cards 1 to 54 once",
+            ));
+        }
+
+        Ok(Self {
+            deck: deck.to_vec(),
+        })
+    }
+
+    pub fn deck(&self) -> &[u8] {
+        &self.deck
+    }
+
+    // Moves a joker down the deck, wrapping round below the top card rather than above it
+    fn move_down(&mut self, joker: u8, by: usize) {
+        let mut i = self.deck.iter().position(|&c| c == joker).unwrap();
+
+        for _ in 0..by {
+            if i == DECK_SIZE - 1 {
+                let card = self.deck.remove(i);
+                self.deck.insert(1, card);
+                i = 1;
+            } else {
+                self.deck.swap(i, i + 1);
+                i += 1;
+            }
+        }
+    }
+
+    // Swaps the cards above the first joker with those below the second
+    fn triple_cut(&mut self) {
+        let a = self.deck.iter().position(|&c| c == JOKER_A).unwrap();
+        let b = self.deck.iter().position(|&c| c == JOKER_B).unwrap();
+        let (first, second) = (a.min(b), a.max(b));
+
+        let mut deck = self.deck[second + 1..].to_vec();
+        deck.extend_from_slice(&self.deck[first..=second]);
+        deck.extend_from_slice(&self.deck[..first]);
+        self.deck = deck;
+    }
+
+    // Moves that many cards from the top to just above the bottom card
+    fn count_cut(&mut self, count: u8) {
+        let count = usize::from(count).min(DECK_SIZE - 1);
+        let bottom = self.deck.pop().unwrap();
+        self.deck.rotate_left(count);
+        self.deck.push(bottom);
+    }
+
+    fn value(card: u8) -> u8 {
+        card.min(JOKER_A)
+    }
+
+    fn step(&mut self) {
+        self.move_down(JOKER_A, 1);
+        self.move_down(JOKER_B, 2);
+        self.triple_cut();
+        self.count_cut(Self::value(self.deck[DECK_SIZE - 1]));
+    }
+
+    // The next value of the keystream, from 1 to 26. Jokers give no output.
+    fn next_key(&mut self) -> u8 {
+        loop {
+            self.step();
+
+            let card = self.deck[usize::from(Self::value(self.deck[0]))];
+            if card < JOKER_A {
+                return (card - 1) % Char::MAX + 1;
+            }
+        }
+    }
+
+    // The keystream for a message, without changing the deck
+    pub fn keystream(&self, len: usize) -> Vec<u8> {
+        let mut deck = self.clone();
+        (0..len).map(|_| deck.next_key()).collect()
+    }
+}
+
+impl PartialEncrypt for Solitaire {
+    fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        let mut deck = self.clone();
+
+        for c in &mut buf {
+            *c += deck.next_key();
+        }
+
+        Ok(buf)
+    }
+}
+
+impl PartialDecrypt for Solitaire {
+    fn decrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
+        let mut deck = self.clone();
+
+        for c in &mut buf {
+            *c -= deck.next_key();
+        }
+
+        Ok(buf)
+    }
+}
+
+derive_encrypt_decrypt!(Solitaire, SimpleError);
+
+impl fmt::Display for Solitaire {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let deck = self
+            .deck
+            .iter()
+            .map(|c| match *c {
+                JOKER_A => "A".to_string(),
+                JOKER_B => "B".to_string(),
+                c => c.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", deck.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors() {
+        // From Schneier's published test vectors, each enciphering a run of As
+        let vectors = [
+            ("", "EXKYIZSGEHUNTIQ"),
+            ("f", "XYIUQBMHKKJBEGY"),
+            ("fo", "TUJYMBERLGXNDIW"),
+            ("foo", "ITHZUJIWGRFARMW"),
+            ("a", "XODALGSCULIQNSC"),
+            ("aa", "OHGWMXXCAIMCIQP"),
+            ("aaa", "DCSQYHBQZNGDRUT"),
+            ("b", "XQEEMOITLZVDSQS"),
+            ("bc", "QNGRKQIHCLGWSCE"),
+            ("bcd", "FMUBYBMAXHNQXCJ"),
+            ("cryptonomicon", "SUGSRSXSWQRMXOHIPBFPXARYQ"),
+        ];
+
+        for &(passphrase, ciphertext) in &vectors {
+            let mut solitaire = Solitaire::new(passphrase);
+            let plaintext = "A".repeat(ciphertext.len());
+
+            let buf = solitaire.encrypt(Buffer::from(&plaintext)).unwrap();
+            assert_eq!(ciphertext, buf.to_string());
+
+            let buf = solitaire.decrypt(buf).unwrap();
+            assert_eq!(plaintext, buf.to_string());
+        }
+    }
+
+    #[test]
+    fn test_keystream() {
+        let solitaire = Solitaire::unkeyed();
+        assert_eq!(vec![4, 23, 10, 24, 8], solitaire.keystream(5));
+
+        // Encrypting leaves the keyed deck as it was
+        let mut solitaire = Solitaire::new("cryptonomicon");
+        let deck = solitaire.clone();
+        solitaire.encrypt(Buffer::from("Solitaire")).unwrap();
+        assert_eq!(deck, solitaire);
+    }
+
+    #[test]
+    fn test_from_deck() {
+        let keyed = Solitaire::new("cryptonomicon");
+        let mut solitaire = Solitaire::from_deck(keyed.deck()).unwrap();
+
+        let buf = solitaire.encrypt(Buffer::from("SOLITAIRE")).unwrap();
+        assert_eq!("KIRAKSFJA", buf.to_string());
+
+        assert!(Solitaire::from_deck(&[1, 2, 3]).is_err());
+        assert!(Solitaire::from_deck(&[1; DECK_SIZE]).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt, Resumable};
 use crate::meta::HeuristicTarget;
 use crate::{Buffer, Char, PartialBuffer};
 use rand::{seq::SliceRandom, Rng};
@@ -22,14 +22,15 @@ impl Substitution {
         out
     }
 
-    pub fn from_word(word: &str) -> Self {
+    // The letters of `word` without repeats, followed by the rest of the alphabet in order
+    pub fn keyed_alphabet(word: &str) -> [Char; Char::MAX as usize] {
         // TODO: This can be cleaned up quite a lot, probably
         let mut duplicate_buffer = [false; Char::MAX as usize];
         let mut buffer = [Char::from('a'); Char::MAX as usize];
         let mut buf_pos = 0;
 
         for ch in word.chars() {
-            if !ch.is_ascii() {
+            if !ch.is_ascii_alphabetic() {
                 continue;
             }
 
@@ -56,8 +57,12 @@ impl Substitution {
 
         assert!(buf_pos == Char::MAX as usize);
 
+        buffer
+    }
+
+    pub fn from_word(word: &str) -> Self {
         Self {
-            key: buffer,
+            key: Substitution::keyed_alphabet(word),
             encrypt_mode: true,
         }
     }
@@ -66,11 +71,9 @@ impl Substitution {
         assert!(alphabet.len() == Char::MAX as usize);
 
         let mut buffer = [Char::from('a'); Char::MAX as usize];
-        let mut buf_pos = 0;
 
-        for ch in alphabet.chars() {
+        for (buf_pos, ch) in alphabet.chars().enumerate() {
             buffer[buf_pos] = Char::from(ch);
-            buf_pos += 1;
         }
 
         Self {
@@ -85,7 +88,7 @@ impl PartialEq for Substitution {
         if self.encrypt_mode == other.encrypt_mode {
             self.key == other.key
         } else {
-            self.key == Substitution::compute_inverse(other.key.clone())
+            self.key == Substitution::compute_inverse(other.key)
         }
     }
 }
@@ -94,6 +97,8 @@ impl Eq for Substitution {}
 
 impl PartialEncrypt for Substitution {
     fn encrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
         if !self.encrypt_mode {
             self.encrypt_mode = true;
             self.key = Substitution::compute_inverse(self.key);
@@ -109,6 +114,8 @@ impl PartialEncrypt for Substitution {
 
 impl PartialDecrypt for Substitution {
     fn decrypt_partial(&mut self, mut buf: PartialBuffer) -> Result<PartialBuffer, Self::Error> {
+        check_alphabet!(&buf, SimpleError);
+
         if self.encrypt_mode {
             self.encrypt_mode = false;
             self.key = Substitution::compute_inverse(self.key);
@@ -124,6 +131,10 @@ impl PartialDecrypt for Substitution {
 
 derive_encrypt_decrypt!(Substitution, SimpleError);
 
+impl Resumable for Substitution {
+    fn skip(&mut self, _: usize) {}
+}
+
 impl fmt::Display for Substitution {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let true_key = if self.encrypt_mode {
@@ -191,6 +202,19 @@ impl HeuristicTarget for Substitution {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyword() {
+        // Spaces, digits and punctuation in the keyword are passed over
+        let sub1 = Substitution::from_word("ZEBRAS");
+        let sub2 = Substitution::from_word("Zebras 42!");
+        assert_eq!(sub1, sub2);
+    }
+}
+
 /*#[cfg(test)]
 mod tests {
     use super::*;
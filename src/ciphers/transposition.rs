@@ -1,12 +1,11 @@
 use super::{Decrypt, Encrypt};
 use crate::Buffer;
-use crate::Char;
 use itertools::Itertools;
 use simple_error::SimpleError;
 use smallvec::SmallVec;
-use std::iter::FromIterator;
 use std::str::FromStr;
 
+// Only moves letters about, so works in any alphabet
 pub struct Transposition {
     key: SmallVec<[u8; 32]>,
 }
@@ -27,19 +26,16 @@ impl Encrypt for Transposition {
 
     fn encrypt(&mut self, mut buf: Buffer) -> Result<Buffer, Self::Error> {
         let keylen = self.key.len();
-        let mut ciphertext = String::from("");
+        let mut ciphertext = Vec::new();
 
         for chunk in &buf.into_iter().chunks(keylen) {
-            let group = chunk.collect::<Vec<_>>();
-            let new_key = self.key.clone();
-            let mut cipher_group = group.iter().zip(new_key).collect::<Vec<_>>();
-            cipher_group.sort_unstable_by(|a, b| a.1.cmp(&b.1));
-            let cipher_string = cipher_group.into_iter().map(|a| char::from(**a.0));
-            ciphertext += &String::from_iter(cipher_string);
+            let mut cipher_group = chunk.zip(&self.key).collect::<Vec<_>>();
+            cipher_group.sort_unstable_by_key(|a| a.1);
+            ciphertext.extend(cipher_group.into_iter().map(|a| *a.0));
         }
 
-        for (i, x) in (&mut buf).into_iter().enumerate() {
-            *x = Char::from(ciphertext.as_bytes()[i] as char);
+        for (x, c) in (&mut buf).into_iter().zip(ciphertext) {
+            *x = c;
         }
 
         Ok(buf)
@@ -51,19 +47,16 @@ impl Decrypt for Transposition {
 
     fn decrypt(&mut self, mut buf: Buffer) -> Result<Buffer, Self::Error> {
         let keylen = self.key.len();
-        let mut plaintext = String::from("");
+        let mut plaintext = Vec::new();
 
         for chunk in &buf.into_iter().chunks(keylen) {
-            let group = chunk.collect::<Vec<_>>();
-            let new_key = self.key.clone();
-            let mut plain_group = group.iter().zip(new_key).collect::<Vec<_>>();
-            plain_group.sort_unstable_by(|a, b| a.1.cmp(&b.1));
-            let plain_string = plain_group.into_iter().map(|a| char::from(**a.0));
-            plaintext += &String::from_iter(plain_string);
+            let mut plain_group = chunk.zip(&self.key).collect::<Vec<_>>();
+            plain_group.sort_unstable_by_key(|a| a.1);
+            plaintext.extend(plain_group.into_iter().map(|a| *a.0));
         }
 
-        for (i, x) in (&mut buf).into_iter().enumerate() {
-            *x = Char::from(plaintext.as_bytes()[i] as char);
+        for (x, c) in (&mut buf).into_iter().zip(plaintext) {
+            *x = c;
         }
 
         Ok(buf)
@@ -73,6 +66,8 @@ impl Decrypt for Transposition {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Alphabet;
+    use std::sync::Arc;
 
     #[test]
     fn test_encrypt_decrypt() {
@@ -85,4 +80,17 @@ mod tests {
         let buf = transposition.decrypt(buf).unwrap();
         assert_eq!("HELLOWORLD", buf.to_string());
     }
+
+    #[test]
+    fn test_alphabet() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("HELLO2WORLD", alphanumeric).unwrap();
+
+        let mut transposition = Transposition::new("2,1");
+        let buf = transposition.encrypt(buf).unwrap();
+        assert_eq!("EHLL2OOWLRD", buf.to_string());
+
+        let buf = transposition.decrypt(buf).unwrap();
+        assert_eq!("HELLO2WORLD", buf.to_string());
+    }
 }
@@ -0,0 +1,241 @@
+use super::StraddlingCheckerboard;
+use crate::digits::chain_addition;
+use crate::{Buffer, DigitBuffer};
+use simple_error::SimpleError;
+
+// VIC-style pipeline: straddling checkerboard, optional additive key, then a simple columnar
+// transposition followed by a disrupted (triangular area) columnar transposition
+#[derive(Debug, Clone)]
+pub struct Vic {
+    checkerboard: StraddlingCheckerboard,
+    additive: Vec<u8>,
+    first: Vec<u8>,
+    second: Vec<u8>,
+}
+
+impl Vic {
+    // Transposition keys are digit sequences ranked VIC-style, i.e. 0 counts as 10
+    pub fn new(
+        checkerboard: StraddlingCheckerboard,
+        first: &[u8],
+        second: &[u8],
+    ) -> Result<Self, SimpleError> {
+        if first.is_empty() || second.is_empty() {
+            return Err(SimpleError::new("transposition keys must not be empty"));
+        }
+
+        Ok(Self {
+            checkerboard,
+            additive: Vec::new(),
+            first: first.to_vec(),
+            second: second.to_vec(),
+        })
+    }
+
+    // Adds a numeric key to the checkerboard output before transposing. The key is the seed
+    // followed by its chain addition, so it runs as long as the message without repeating.
+    pub fn with_additive(mut self, seed: &[u8]) -> Result<Self, SimpleError> {
+        if seed.len() < 2 {
+            return Err(SimpleError::new(
+                "additive seed must have at least two digits",
+            ));
+        }
+
+        if seed.iter().any(|&d| d >= 10) {
+            return Err(SimpleError::new("additive seed must be decimal digits"));
+        }
+
+        self.additive = seed.to_vec();
+        Ok(self)
+    }
+
+    fn additive_key(&self, len: usize) -> Vec<u8> {
+        let mut key = self.additive.clone();
+        key.extend(chain_addition(
+            &self.additive,
+            len.saturating_sub(key.len()),
+        ));
+        key
+    }
+
+    pub fn encrypt(&self, buf: &Buffer) -> Result<DigitBuffer, SimpleError> {
+        let mut digits = self.checkerboard.encode(buf)?;
+
+        if !self.additive.is_empty() {
+            digits.add_key(&self.additive_key(digits.len()));
+        }
+
+        let digits = columnar_encrypt(&digits, &self.first, false);
+        Ok(columnar_encrypt(&digits, &self.second, true))
+    }
+
+    pub fn decrypt(&self, digits: &DigitBuffer) -> Result<Buffer, SimpleError> {
+        let digits = columnar_decrypt(digits, &self.second, true);
+        let mut digits = columnar_decrypt(&digits, &self.first, false);
+
+        if !self.additive.is_empty() {
+            digits.sub_key(&self.additive_key(digits.len()));
+        }
+
+        self.checkerboard.decode(&digits)
+    }
+}
+
+// Column indices in the order they are read out
+pub fn column_order(key: &[u8]) -> Vec<usize> {
+    let rank = |d: u8| if d == 0 { 10 } else { d };
+
+    let mut order = (0..key.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| rank(key[i])); // Stable, so ties are read left to right
+    order
+}
+
+// Order in which the grid cells are filled. Without disruption this is row by row; with it,
+// the cells outside the triangular areas are filled first and the areas afterwards.
+fn fill_order(len: usize, order: &[usize], disrupted: bool) -> Vec<usize> {
+    let width = order.len();
+
+    if !disrupted {
+        return (0..len).collect();
+    }
+
+    let rows = len.div_ceil(width);
+    let mut in_area = vec![false; rows * width];
+    let mut row = 0;
+
+    // Each area starts under the next column in key order, and narrows by one cell per row
+    // until it is empty, after which the next area begins
+    for &start in order.iter().cycle() {
+        if row >= rows {
+            break;
+        }
+
+        for (offset, r) in (row..rows).enumerate() {
+            row = r + 1;
+
+            if start + offset >= width {
+                break;
+            }
+
+            for c in start + offset..width {
+                in_area[r * width + c] = true;
+            }
+        }
+    }
+
+    let (mut open, area): (Vec<usize>, Vec<usize>) = (0..len).partition(|&i| !in_area[i]);
+    open.extend(area);
+    open
+}
+
+fn read_order(len: usize, order: &[usize]) -> Vec<usize> {
+    let width = order.len();
+
+    order
+        .iter()
+        .flat_map(|&c| (c..len).step_by(width))
+        .collect()
+}
+
+fn columnar_encrypt(digits: &DigitBuffer, key: &[u8], disrupted: bool) -> DigitBuffer {
+    let order = column_order(key);
+    let data = digits.as_slice();
+
+    let mut grid = vec![0; data.len()];
+    for (&cell, &d) in fill_order(data.len(), &order, disrupted).iter().zip(data) {
+        grid[cell] = d;
+    }
+
+    DigitBuffer::new(
+        read_order(data.len(), &order)
+            .into_iter()
+            .map(|cell| grid[cell])
+            .collect(),
+    )
+}
+
+fn columnar_decrypt(digits: &DigitBuffer, key: &[u8], disrupted: bool) -> DigitBuffer {
+    let order = column_order(key);
+    let data = digits.as_slice();
+
+    let mut grid = vec![0; data.len()];
+    for (&cell, &d) in read_order(data.len(), &order).iter().zip(data) {
+        grid[cell] = d;
+    }
+
+    DigitBuffer::new(
+        fill_order(data.len(), &order, disrupted)
+            .into_iter()
+            .map(|cell| grid[cell])
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columnar() {
+        let digits = DigitBuffer::from("0123456789");
+
+        let out = columnar_encrypt(&digits, &[3, 1, 2], false);
+        assert_eq!(DigitBuffer::from("1472580369"), out);
+        assert_eq!(digits, columnar_decrypt(&out, &[3, 1, 2], false));
+
+        let out = columnar_encrypt(&digits, &[3, 1, 0, 2], true);
+        assert_eq!(digits, columnar_decrypt(&out, &[3, 1, 0, 2], true));
+    }
+
+    #[test]
+    fn test_disrupted() {
+        // Worked by hand. The key reads columns 1, 3, 0, 2, so the area starts under column 1
+        // and narrows to the right edge:
+        //
+        //   0 [6 7 8]
+        //   1  2 [9 0]
+        //   3  4  5 [1]
+        //
+        // The open cells take 0 to 5 and the area cells 6 to 1, each row by row.
+        let digits = DigitBuffer::from("012345678901");
+
+        let out = columnar_encrypt(&digits, &[3, 1, 0, 2], true);
+        assert_eq!(DigitBuffer::from("624801013795"), out);
+        assert_eq!(digits, columnar_decrypt(&out, &[3, 1, 0, 2], true));
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let board = StraddlingCheckerboard::new("ESTONIAR", (2, 6))
+            .unwrap()
+            .with_header([4, 0, 7, 1, 9, 3, 8, 2, 6, 5])
+            .unwrap();
+        let vic = Vic::new(board, &[5, 2, 8, 0, 1, 3, 7], &[9, 4, 1, 6, 0, 2, 3, 5, 8])
+            .unwrap()
+            .with_additive(&[7, 7, 6, 5, 1])
+            .unwrap();
+        let buf = Buffer::from("WE ARE PLEASED TO HEAR OF YOUR SUCCESS IN ESTABLISHING YOUR COVER");
+
+        let digits = vic.encrypt(&buf).unwrap();
+        assert_ne!(vic.checkerboard.encode(&buf).unwrap(), digits);
+
+        let out = vic.decrypt(&digits).unwrap();
+        assert_eq!(
+            "WEAREPLEASEDTOHEAROFYOURSUCCESSINESTABLISHINGYOURCOVER",
+            out.to_string()
+        );
+    }
+
+    #[test]
+    fn test_additive() {
+        let board = StraddlingCheckerboard::new("ESTONIAR", (2, 6)).unwrap();
+        let vic = Vic::new(board, &[1], &[1]).unwrap();
+
+        assert!(vic.clone().with_additive(&[7]).is_err());
+        assert!(vic.clone().with_additive(&[7, 10, 6]).is_err());
+
+        // The seed, then its chain addition
+        let vic = vic.with_additive(&[7, 7, 0, 4, 1]).unwrap();
+        assert_eq!(vec![7, 7, 0, 4, 1, 4, 7, 4], vic.additive_key(8));
+    }
+}
@@ -1,10 +1,12 @@
-use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt};
+use super::{Decrypt, Encrypt, PartialDecrypt, PartialEncrypt, Resumable};
 use crate::{Buffer, PartialBuffer};
 use simple_error::SimpleError;
 use smallvec::SmallVec;
 
 pub struct Vigenere {
     key: SmallVec<[u8; 32]>,
+    // Where in the key the next letter starts, once earlier pieces have been skipped
+    offset: usize,
 }
 
 impl Vigenere {
@@ -16,12 +18,16 @@ impl Vigenere {
             .map(|c| (c.to_ascii_uppercase() as u8) - b'A')
             .collect::<SmallVec<[u8; 32]>>();
 
-        Self { key: sv }
+        Self { key: sv, offset: 0 }
     }
 
+    /// # Safety
+    ///
+    /// Every element of `key` must be less than `Char::MAX`.
     pub unsafe fn new_unchecked(key: &[u8]) -> Self {
         Self {
             key: SmallVec::from(key),
+            offset: 0,
         }
     }
 }
@@ -31,7 +37,7 @@ impl PartialEncrypt for Vigenere {
         let keylen = self.key.len();
 
         for (i, b) in (&mut buf).into_iter().enumerate() {
-            *b += self.key[i % keylen];
+            *b += self.key[(i + self.offset) % keylen];
         }
 
         Ok(buf)
@@ -43,7 +49,7 @@ impl PartialDecrypt for Vigenere {
         let keylen = self.key.len();
 
         for (i, b) in (&mut buf).into_iter().enumerate() {
-            *b -= self.key[i % keylen];
+            *b -= self.key[(i + self.offset) % keylen];
         }
 
         Ok(buf)
@@ -52,6 +58,12 @@ impl PartialDecrypt for Vigenere {
 
 derive_encrypt_decrypt!(Vigenere, SimpleError);
 
+impl Resumable for Vigenere {
+    fn skip(&mut self, n: usize) {
+        self.offset = (self.offset + n) % self.key.len();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,4 +79,14 @@ mod tests {
         let buf = vigenere.decrypt(buf).unwrap();
         assert_eq!("Hello world!", buf.to_string());
     }
+
+    #[test]
+    fn test_skip() {
+        let mut vigenere = Vigenere::new("KEY");
+        vigenere.skip(4);
+
+        let buf = vigenere.encrypt(Buffer::from("o world!")).unwrap();
+        assert_eq!("s uyvjn!", buf.to_string());
+        assert_eq!(&[10, 4, 24], &vigenere.key[..]);
+    }
 }
@@ -0,0 +1,122 @@
+use crate::{Buffer, CharStream};
+use std::fmt;
+
+// How closely a buffer, such as the output of a crack, matches the plaintext it should be.
+// Letters are compared position by position, so a dropped letter throws out everything after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    // The letters of the buffer, each with whether it matches, then any the buffer lacks
+    letters: Vec<(char, bool)>,
+    missing: usize,
+}
+
+pub fn compare(actual: &Buffer, expected: &Buffer) -> Comparison {
+    let symbols = |buf: &Buffer| {
+        let alphabet = buf.alphabet();
+        buf.into_iter()
+            .map(|&c| alphabet.symbol(c))
+            .collect::<Vec<_>>()
+    };
+
+    let (actual, expected) = (symbols(actual), symbols(expected));
+    let letters = actual
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (c, expected.get(i) == Some(&c)))
+        .collect();
+
+    Comparison {
+        letters,
+        missing: expected.len().saturating_sub(actual.len()),
+    }
+}
+
+impl Comparison {
+    pub fn is_exact(&self) -> bool {
+        self.missing == 0 && self.letters.iter().all(|l| l.1)
+    }
+
+    pub fn correct(&self) -> usize {
+        self.letters.iter().filter(|l| l.1).count()
+    }
+
+    // The fraction of positions that match, out of the longer of the two buffers
+    pub fn accuracy(&self) -> f64 {
+        let len = self.letters.len() + self.missing;
+
+        if len == 0 {
+            1.0
+        } else {
+            self.correct() as f64 / len as f64
+        }
+    }
+
+    pub fn longest_correct_run(&self) -> usize {
+        self.letters
+            .split(|l| !l.1)
+            .map(|run| run.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // The buffer's letters with those that are wrong in lower case, and a - for each missing
+    pub fn diff(&self) -> String {
+        let letters = self.letters.iter().map(|&(c, correct)| {
+            if correct {
+                c
+            } else {
+                c.to_lowercase().next().unwrap_or(c)
+            }
+        });
+
+        letters
+            .chain(std::iter::repeat_n('-', self.missing))
+            .collect()
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.diff())?;
+        write!(
+            f,
+            "{:.1}% correct, longest correct run {}",
+            self.accuracy() * 100.0,
+            self.longest_correct_run()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let expected = Buffer::from("Attack at dawn");
+
+        let comparison = compare(&Buffer::from("attack at dawn!"), &expected);
+        assert!(comparison.is_exact());
+        assert_eq!(1.0, comparison.accuracy());
+
+        let comparison = compare(&Buffer::from("Attack it down"), &expected);
+        assert!(!comparison.is_exact());
+        assert_eq!(10, comparison.correct());
+        assert_eq!(6, comparison.longest_correct_run());
+        assert_eq!("ATTACKiTDoWN", comparison.diff());
+
+        let comparison = compare(&Buffer::from("Attack at"), &expected);
+        assert!(!comparison.is_exact());
+        assert_eq!(8, comparison.correct());
+        assert_eq!("ATTACKAT----", comparison.diff());
+        assert_eq!(
+            "ATTACKAT----\n66.7% correct, longest correct run 8",
+            comparison.to_string()
+        );
+
+        let comparison = compare(&Buffer::from("Attack at dawn today"), &expected);
+        assert!(!comparison.is_exact());
+        assert_eq!(12, comparison.correct());
+        assert_eq!("ATTACKATDAWNtoday", comparison.diff());
+    }
+}
@@ -0,0 +1,163 @@
+use simple_error::SimpleError;
+use std::str::FromStr;
+use std::{fmt, slice};
+
+// A stream of decimal digits, as produced by straddling checkerboards and other numeric systems
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DigitBuffer {
+    data: Vec<u8>,
+}
+
+impl DigitBuffer {
+    pub fn new(data: Vec<u8>) -> Self {
+        debug_assert!(data.iter().all(|&d| d < 10));
+        Self { data }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn push(&mut self, digit: u8) {
+        debug_assert!(digit < 10);
+        self.data.push(digit);
+    }
+
+    // Non-carrying addition of a repeating key, digit by digit
+    pub fn add_key(&mut self, key: &[u8]) {
+        assert!(!key.is_empty(), "key is empty");
+
+        for (d, &k) in self.data.iter_mut().zip(key.iter().cycle()) {
+            *d = (*d + k % 10) % 10;
+        }
+    }
+
+    // Non-carrying subtraction of a repeating key, the inverse of `add_key`
+    pub fn sub_key(&mut self, key: &[u8]) {
+        assert!(!key.is_empty(), "key is empty");
+
+        for (d, &k) in self.data.iter_mut().zip(key.iter().cycle()) {
+            *d = (*d + 10 - k % 10) % 10;
+        }
+    }
+}
+
+// Extends `seed` to `len` digits, each new digit being the sum (mod 10) of the
+// first two digits of the previous window (a lagged Fibonacci generator)
+pub fn chain_addition(seed: &[u8], len: usize) -> Vec<u8> {
+    assert!(seed.len() >= 2, "seed must have at least two digits");
+
+    let mut out = seed.to_vec();
+    let lag = seed.len();
+
+    while out.len() < len + lag {
+        let i = out.len() - lag;
+        out.push((out[i] + out[i + 1]) % 10);
+    }
+
+    out.split_off(lag)
+}
+
+impl<'a> IntoIterator for &'a DigitBuffer {
+    type Item = &'a u8;
+    type IntoIter = slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut DigitBuffer {
+    type Item = &'a mut u8;
+    type IntoIter = slice::IterMut<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter_mut()
+    }
+}
+
+impl From<Vec<u8>> for DigitBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        DigitBuffer::new(data)
+    }
+}
+
+impl FromStr for DigitBuffer {
+    type Err = SimpleError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        data.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                c.to_digit(10)
+                    .map(|d| d as u8)
+                    .ok_or_else(|| SimpleError::new("string contains non-digit characters"))
+            })
+            .collect::<Result<Vec<u8>, _>>()
+            .map(DigitBuffer::new)
+    }
+}
+
+impl From<&str> for DigitBuffer {
+    fn from(data: &str) -> Self {
+        DigitBuffer::from_str(data).unwrap()
+    }
+}
+
+impl fmt::Display for DigitBuffer {
+    // Digits are written in the traditional groups of five
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, chunk) in self.data.chunks(5).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            for d in chunk {
+                write!(f, "{}", d)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_display() {
+        let digits = DigitBuffer::from("31132 12731\n22365 5");
+
+        assert_eq!(16, digits.len());
+        assert_eq!("31132 12731 22365 5", digits.to_string());
+        assert!(DigitBuffer::from_str("123A5").is_err());
+    }
+
+    #[test]
+    fn test_add_sub_key() {
+        let mut digits = DigitBuffer::from("0123456789");
+
+        digits.add_key(&[5, 9]);
+        assert_eq!(DigitBuffer::from("5072941638"), digits);
+
+        digits.sub_key(&[5, 9]);
+        assert_eq!(DigitBuffer::from("0123456789"), digits);
+    }
+
+    #[test]
+    fn test_chain_addition() {
+        assert_eq!(
+            vec![4, 7, 4, 5, 5, 1, 1, 9],
+            chain_addition(&[7, 7, 0, 4, 1], 8)
+        );
+    }
+}
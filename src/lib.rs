@@ -1,9 +1,22 @@
+pub mod alphabet;
+pub mod baudot;
 pub mod buffer;
 pub mod char;
 pub mod ciphers;
+pub mod compare;
+pub mod digits;
 pub mod meta;
+pub mod morse;
 pub mod score;
 pub mod stats;
+pub mod stream;
+pub mod tokens;
 
-pub use self::buffer::{Buffer, CharStream, PartialBuffer};
+pub use self::alphabet::Alphabet;
+pub use self::baudot::BaudotBuffer;
+pub use self::buffer::{Buffer, CharStream, Format, PartialBuffer};
 pub use self::char::Char;
+pub use self::digits::DigitBuffer;
+pub use self::morse::MorseBuffer;
+pub use self::stream::CharReader;
+pub use self::tokens::TokenBuffer;
@@ -1,7 +1,12 @@
-use clap::{App, Arg, SubCommand};
-use kaiser::ciphers::Decrypt;
-use kaiser::ciphers::Encrypt;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use kaiser::alphabet::{Alphabet, Policy};
+use kaiser::ciphers::{Decrypt, Encrypt, Resumable};
+use kaiser::compare::compare;
+use kaiser::stream::{self, CharReader};
+use kaiser::{CharStream, Format};
 use std::io::{self, Read};
+use std::sync::Arc;
+use std::{fs, process};
 
 #[macro_use]
 extern crate scan_fmt;
@@ -11,6 +16,14 @@ fn main() {
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
+        .arg(
+            Arg::with_name("letters")
+                .long("letters")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&["reject", "strip", "keep", "transliterate"])
+                .help("What to do with accented and other letters outside the alphabet"),
+        )
         .subcommand(SubCommand::with_name("ioc").about("Calculates the index of coincidence"))
         .subcommand(
             SubCommand::with_name("chi")
@@ -21,6 +34,17 @@ fn main() {
             SubCommand::with_name("quadgrams")
                 .about("Generates a quadgram score for the text (higher is better)"),
         )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Shows how closely the text matches a known plaintext")
+                .arg(
+                    Arg::with_name("plaintext")
+                        .long("plaintext")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The file holding the plaintext"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("trim")
                 .about(
@@ -38,6 +62,12 @@ fn main() {
                         .long("offset")
                         .takes_value(true)
                         .help("Start taking characters from this offset"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .help("Write the output as layout, letters or groups[:size]"),
                 ),
         )
         .subcommand(
@@ -56,6 +86,12 @@ fn main() {
                         .long("key")
                         .takes_value(true)
                         .help("Provide the key to use"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .help("Write the output as layout, letters or groups[:size]"),
                 ),
         )
         .subcommand(
@@ -74,29 +110,60 @@ fn main() {
                         .long("key")
                         .takes_value(true)
                         .help("Provide the key to use"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .help("Write the output as layout, letters or groups[:size]"),
                 ),
         );
 
     let matches = app.clone().get_matches();
 
+    let policy = match matches.value_of("letters") {
+        Some("strip") => Policy::Strip,
+        Some("keep") => Policy::Keep,
+        Some("transliterate") => Policy::Transliterate,
+        _ => Policy::Reject,
+    };
+
     match matches.subcommand() {
         ("ioc", Some(_)) => {
-            println!("{}", kaiser::stats::index_of_coincidence(&input()));
+            println!(
+                "{}",
+                exit_on_error(stream::index_of_coincidence(reader(policy)))
+            );
         }
         ("chi", Some(_)) => {
-            println!("{}", kaiser::stats::chi_squared(&input()));
+            println!("{}", exit_on_error(stream::chi_squared(reader(policy))));
         }
         ("quadgrams", Some(_)) => {
-            println!("{}", kaiser::stats::quadgram_score(&input()));
+            println!("{}", exit_on_error(stream::quadgram_score(reader(policy))));
         }
         ("freqs", Some(_)) => {
-            for (i, freq) in kaiser::stats::letter_frequencies(&input())
-                .iter()
-                .enumerate()
-            {
-                println!("{}: {}", (b'A' + i as u8) as char, freq);
+            let alphabet = Alphabet::english();
+            let freqs = exit_on_error(stream::letter_frequencies(reader(policy)));
+
+            for (symbol, freq) in alphabet.symbols().iter().zip(freqs) {
+                println!("{}: {}", symbol, freq);
             }
         }
+        ("compare", Some(matches)) => {
+            let path = matches.value_of("plaintext").unwrap();
+            let text = fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("Unable to read {}: {}", path, e);
+                process::exit(1);
+            });
+
+            let alphabet = Arc::new((*Alphabet::english()).clone().with_policy(policy));
+            let plaintext = kaiser::Buffer::parse(&text, alphabet).unwrap_or_else(|e| {
+                eprintln!("Unable to read {}: {}", path, e);
+                process::exit(1);
+            });
+
+            println!("{}", compare(&input(policy), &plaintext));
+        }
         ("trim", Some(matches)) => {
             let stride = matches.value_of("stride").map_or(1, |s| {
                 s.parse::<usize>()
@@ -110,20 +177,23 @@ fn main() {
                     .expect("offset must be a positive integer")
             });
 
-            let mut s = String::new();
-            io::stdin()
-                .read_to_string(&mut s)
-                .expect("unable to read from stdin");
+            // Letters outside the alphabet are stripped unless asked otherwise
+            let policy = matches
+                .value_of("letters")
+                .map_or(Policy::Strip, |_| policy);
 
-            let out = s
-                .chars()
-                .filter(|c| c.is_ascii() && c.is_alphabetic())
-                .map(|c| c.to_ascii_uppercase())
+            let buf = input(policy);
+            let letters = buf
+                .into_iter()
+                .map(|&c| char::from(c))
                 .skip(offset)
                 .step_by(stride)
                 .collect::<String>();
 
-            println!("{}", out);
+            output(
+                &kaiser::Buffer::from(&letters),
+                format(matches, Format::Letters),
+            );
         }
         ("decrypt", Some(matches)) => {
             let key = match matches.value_of("key") {
@@ -136,31 +206,54 @@ fn main() {
             match matches.value_of("type") {
                 Some("caesar") => {
                     // if user tries to provide a string, default to 0 and don't encrypt.
-                    let mut caesar =
+                    let caesar =
                         kaiser::ciphers::Caesar::new(key.parse::<u8>().unwrap_or_else(|_| {
                             println!(
                                 "Invalid key provided (must be a single integer), defaulting to 0"
                             );
                             0
                         }));
-                    let buf = caesar.decrypt(input()).unwrap();
-                    print!("{}", buf.to_string());
+                    run(caesar, true, policy, format(matches, Format::Layout));
                 }
                 Some("affine") => {
                     // try to read two u8 separated by "," otherwise default to the values of 1,0 (i.e., don't encrypt at all)
                     let (a, b) = scan_fmt!(key, "{},{}", u8, u8);
                     let shift = a.unwrap_or_else(|| {println!("Invalid key provided for a (must be a single integer), defaulting to 1"); 1});
                     let mult = b.unwrap_or_else(|| {println!("Invalid key provided for b (must be a single integer), defaulting to 0"); 0});
-                    let mut affine = kaiser::ciphers::Affine::new(shift, mult);
-                    let buf = affine.decrypt(input()).unwrap();
-                    print!("{}", buf.to_string());
+                    let affine = kaiser::ciphers::Affine::new(shift, mult);
+                    run(affine, true, policy, format(matches, Format::Layout));
+                }
+                Some("vigenere") if key.starts_with(|c: char| c.is_ascii_digit()) => {
+                    // Numeric keys are shifts, so 0,1,2 is the same as "ABC"
+                    match kaiser::ciphers::Gronsfeld::new(key) {
+                        Ok(gronsfeld) => {
+                            run(gronsfeld, true, policy, format(matches, Format::Layout))
+                        }
+                        Err(e) => println!("Invalid numeric key provided: {}", e),
+                    }
                 }
                 Some("vigenere") => {
-                    // TODO: Write some sane error handling if user tries to provide a numeric key (e.g. treat 1,2,3 as "ABC")
-                    // At the moment it just panics.
-                    let mut vigenere = kaiser::ciphers::Vigenere::new(key);
-                    let buf = vigenere.decrypt(input()).unwrap();
-                    print!("{}", buf.to_string());
+                    let vigenere = kaiser::ciphers::Vigenere::new(key);
+                    run(vigenere, true, policy, format(matches, Format::Layout));
+                }
+                Some("gronsfeld") => {
+                    // e.g. "31415", or "3,14,15" for shifts above 9
+                    match kaiser::ciphers::Gronsfeld::new(key) {
+                        Ok(gronsfeld) => {
+                            run(gronsfeld, true, policy, format(matches, Format::Layout))
+                        }
+                        Err(e) => println!("Invalid gronsfeld key provided: {}", e),
+                    }
+                }
+                Some("enigma") => {
+                    // e.g. "B II,IV,V 02,21,12 BLA AV BS CG DL FU HZ IN KM OW RX"
+                    match key.parse::<kaiser::ciphers::Enigma>() {
+                        Ok(mut enigma) => output(
+                            &enigma.decrypt(input(policy)).unwrap(),
+                            format(matches, Format::Layout),
+                        ),
+                        Err(e) => println!("Invalid enigma key provided: {}", e),
+                    }
                 }
                 Some(_) => println!("Unknown cipher type"),
                 None => println!("No cipher type provided"),
@@ -177,31 +270,54 @@ fn main() {
             match matches.value_of("type") {
                 Some("caesar") => {
                     // if user tries to provide a string, default to 0 and don't encrypt.
-                    let mut caesar =
+                    let caesar =
                         kaiser::ciphers::Caesar::new(key.parse::<u8>().unwrap_or_else(|_| {
                             println!(
                                 "Invalid key provided (must be a single integer), defaulting to 0"
                             );
                             0
                         }));
-                    let buf = caesar.encrypt(input()).unwrap();
-                    print!("{}", buf.to_string());
+                    run(caesar, false, policy, format(matches, Format::Layout));
                 }
                 Some("affine") => {
                     // try to read two u8 separated by "," otherwise default to the values of 1,0 (i.e., don't encrypt at all)
                     let (a, b) = scan_fmt!(key, "{},{}", u8, u8);
                     let shift = a.unwrap_or_else(| | {println!("Invalid key provided for a (must be a single integer), defaulting to 1"); 1});
                     let mult = b.unwrap_or_else(| | {println!("Invalid key provided for b (must be a single integer), defaulting to 0"); 0});
-                    let mut affine = kaiser::ciphers::Affine::new(shift, mult);
-                    let buf = affine.encrypt(input()).unwrap();
-                    print!("{}", buf.to_string());
+                    let affine = kaiser::ciphers::Affine::new(shift, mult);
+                    run(affine, false, policy, format(matches, Format::Layout));
+                }
+                Some("vigenere") if key.starts_with(|c: char| c.is_ascii_digit()) => {
+                    // Numeric keys are shifts, so 0,1,2 is the same as "ABC"
+                    match kaiser::ciphers::Gronsfeld::new(key) {
+                        Ok(gronsfeld) => {
+                            run(gronsfeld, false, policy, format(matches, Format::Layout))
+                        }
+                        Err(e) => println!("Invalid numeric key provided: {}", e),
+                    }
                 }
                 Some("vigenere") => {
-                    // TODO: Write some sane error handling if user tries to provide a numeric key (e.g. treat 1,2,3 as "ABC")
-                    // At the moment it just panics.
-                    let mut vigenere = kaiser::ciphers::Vigenere::new(key);
-                    let buf = vigenere.encrypt(input()).unwrap();
-                    print!("{}", buf.to_string());
+                    let vigenere = kaiser::ciphers::Vigenere::new(key);
+                    run(vigenere, false, policy, format(matches, Format::Layout));
+                }
+                Some("gronsfeld") => {
+                    // e.g. "31415", or "3,14,15" for shifts above 9
+                    match kaiser::ciphers::Gronsfeld::new(key) {
+                        Ok(gronsfeld) => {
+                            run(gronsfeld, false, policy, format(matches, Format::Layout))
+                        }
+                        Err(e) => println!("Invalid gronsfeld key provided: {}", e),
+                    }
+                }
+                Some("enigma") => {
+                    // e.g. "B II,IV,V 02,21,12 BLA AV BS CG DL FU HZ IN KM OW RX"
+                    match key.parse::<kaiser::ciphers::Enigma>() {
+                        Ok(mut enigma) => output(
+                            &enigma.encrypt(input(policy)).unwrap(),
+                            format(matches, Format::Layout),
+                        ),
+                        Err(e) => println!("Invalid enigma key provided: {}", e),
+                    }
                 }
                 Some(_) => println!("Unknown cipher type"),
                 None => println!("No cipher type provided"),
@@ -213,12 +329,75 @@ fn main() {
     }
 }
 
-fn input() -> kaiser::Buffer {
+fn format(matches: &ArgMatches, default: Format) -> Format {
+    match matches.value_of("format").map(str::parse) {
+        Some(Ok(format)) => format,
+        Some(Err(e)) => {
+            eprintln!("Invalid format: {}", e);
+            process::exit(1);
+        }
+        None => default,
+    }
+}
+
+// Ciphers that can take the input a piece at a time are streamed when the layout is kept
+fn run<C>(mut cipher: C, decrypting: bool, policy: Policy, format: Format)
+where
+    C: Encrypt + Decrypt + Resumable,
+{
+    if format == Format::Layout {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        exit_on_error(if decrypting {
+            stream::decrypt(reader(policy), &mut out, &mut cipher)
+        } else {
+            stream::encrypt(reader(policy), &mut out, &mut cipher)
+        });
+    } else if decrypting {
+        output(&cipher.decrypt(input(policy)).unwrap(), format);
+    } else {
+        output(&cipher.encrypt(input(policy)).unwrap(), format);
+    }
+}
+
+fn exit_on_error<T>(result: io::Result<T>) -> T {
+    match result {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Unable to read input: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn reader(policy: Policy) -> CharReader<io::Stdin> {
+    let alphabet = (*Alphabet::english()).clone().with_policy(policy);
+    CharReader::new(io::stdin(), Arc::new(alphabet))
+}
+
+// The layout of the input already ends in its own newline
+fn output(buf: &kaiser::Buffer, format: Format) {
+    match format {
+        Format::Layout => print!("{}", buf),
+        _ => println!("{}", buf.format(format)),
+    }
+}
+
+fn input(policy: Policy) -> kaiser::Buffer {
     let mut s = String::new();
 
     io::stdin()
         .read_to_string(&mut s)
         .expect("unable to read from stdin");
 
-    kaiser::Buffer::from(&s)
+    let alphabet = (*Alphabet::english()).clone().with_policy(policy);
+
+    match kaiser::Buffer::parse(&s, Arc::new(alphabet)) {
+        Ok(buf) => buf,
+        Err(e) => {
+            eprintln!("Unable to read input: {}", e);
+            process::exit(1);
+        }
+    }
 }
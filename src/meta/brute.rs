@@ -11,6 +11,12 @@ impl BruteForce {
     }
 }
 
+impl Default for BruteForce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Metaheuristic for BruteForce {
     fn crack_ciphertext<T: HeuristicTarget>(
         &mut self,
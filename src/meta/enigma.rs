@@ -0,0 +1,391 @@
+use crate::ciphers::enigma::{Reflector, RotorType};
+use crate::ciphers::{Decrypt, Enigma};
+use crate::meta::CrackResults;
+use crate::score::{Score, ScoreMethod};
+use crate::{Buffer, Char, CharStream};
+use itertools::{iproduct, Itertools};
+use simple_error::SimpleError;
+
+const NUM_LETTERS: usize = Char::MAX as usize;
+
+// Ciphertext-only attack on the M3, after Gillogly and Weierud-Sullivan. Every rotor order,
+// starting position and right ring setting is tried with the other rings at A and an empty
+// plugboard, keeping the settings whose decryptions have the highest index of coincidence. The
+// right ring matters at this stage because it decides when the middle rotor steps. The middle
+// ring, and then the plugboard, are recovered from those candidates by hill climbing.
+pub struct EnigmaAttack {
+    reflector: Reflector,
+    rotors: Vec<RotorType>,
+    candidates: usize,
+    progress: bool,
+}
+
+impl EnigmaAttack {
+    pub fn new(reflector: Reflector) -> Self {
+        Self {
+            reflector,
+            rotors: RotorType::ALL.to_vec(),
+            candidates: 10,
+            progress: false,
+        }
+    }
+
+    // Restricts the rotor orders searched to those using the given rotors
+    pub fn with_rotors(mut self, rotors: &[RotorType]) -> Self {
+        self.rotors = rotors.to_vec();
+        self
+    }
+
+    // Number of settings from the rotor search that go on to ring and plugboard recovery
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        assert!(candidates > 0, "candidates was zero");
+
+        self.candidates = candidates;
+        self
+    }
+
+    // Reports progress on stderr, as a full search takes minutes
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn crack_ciphertext(
+        &mut self,
+        text: &Buffer,
+        num_results: usize,
+    ) -> Result<CrackResults<Enigma>, SimpleError> {
+        if self.reflector.is_thin() {
+            return Err(SimpleError::new("only the M3 can be attacked"));
+        }
+
+        if self.rotors.len() < 3 {
+            return Err(SimpleError::new("at least three rotors are needed"));
+        }
+
+        if !text.alphabet().is_english() {
+            return Err(SimpleError::new("cipher needs the English alphabet"));
+        }
+
+        let candidates = self.search_rotors(text)?;
+        let mut results = CrackResults::new(num_results);
+
+        for (i, candidate) in candidates.iter().enumerate() {
+            if self.progress {
+                eprintln!(
+                    "Candidate {}/{}: {}",
+                    i + 1,
+                    candidates.len(),
+                    describe(candidate)
+                );
+            }
+
+            let mut key = candidate.clone();
+            search_rings(&mut key, text, ScoreMethod::IOC)?;
+            climb_plugboard(&mut key, text, ScoreMethod::IOC)?;
+            climb_plugboard(&mut key, text, ScoreMethod::Quadgrams)?;
+
+            // Once most of the plugboard is in, the rings can be placed more accurately
+            search_rings(&mut key, text, ScoreMethod::Quadgrams)?;
+            climb_plugboard(&mut key, text, ScoreMethod::Quadgrams)?;
+
+            let buf = key.decrypt(text.clone())?;
+            let score = results.process_result(buf, key.clone(), ScoreMethod::Quadgrams);
+
+            if self.progress {
+                eprintln!("  -> {} ({:?})", describe(&key), score);
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn search_rotors(&self, text: &Buffer) -> Result<Vec<Enigma>, SimpleError> {
+        let orders = iproduct!(&self.rotors, &self.rotors, &self.rotors)
+            .filter(|(a, b, c)| a != b && b != c && a != c)
+            .map(|(&a, &b, &c)| [a, b, c])
+            .collect::<Vec<_>>();
+        let letters = text.into_iter().map(|&c| u8::from(c)).collect::<Vec<_>>();
+
+        // The best settings found, as (score, order, right ring, positions)
+        let mut best: Vec<(f64, [RotorType; 3], u8, [u8; 3])> = Vec::new();
+
+        for (i, order) in orders.iter().enumerate() {
+            if self.progress {
+                eprintln!(
+                    "Rotor order {}/{}: {}",
+                    i + 1,
+                    orders.len(),
+                    order.iter().map(|r| format!("{:?}", r)).join("-")
+                );
+            }
+
+            let scrambler = Scrambler::new(*order, self.reflector);
+
+            scrambler.search(&letters, |score, ring, positions| {
+                if best.len() < self.candidates || score > best[best.len() - 1].0 {
+                    best.truncate(self.candidates - 1);
+                    let at = best.iter().position(|b| b.0 < score).unwrap_or(best.len());
+                    best.insert(at, (score, *order, ring, positions));
+                }
+            });
+        }
+
+        best.into_iter()
+            .map(|(_, order, ring, positions)| {
+                Enigma::new(self.reflector, &order[..], &[0, 0, ring], &positions, &[])
+            })
+            .collect()
+    }
+}
+
+// The rotors of one order with an empty plugboard and the left and middle rings at A, tabulated
+// so that trying a setting costs a few lookups a letter rather than a full decryption
+struct Scrambler {
+    // Whether the middle and right rotors are at a notch, by position
+    notches: [[bool; NUM_LETTERS]; 2],
+    // The right rotor's wiring in each direction, by its offset
+    right: [[[u8; NUM_LETTERS]; NUM_LETTERS]; 2],
+    // Through the middle and left rotors to the reflector and back, by their offsets
+    inner: Vec<[u8; NUM_LETTERS]>,
+}
+
+// A rotor's wiring turned round by its offset (the position less the ring setting)
+fn through(wiring: &[u8; NUM_LETTERS], offset: usize, c: usize) -> usize {
+    (usize::from(wiring[(c + offset) % NUM_LETTERS]) + NUM_LETTERS - offset) % NUM_LETTERS
+}
+
+impl Scrambler {
+    fn new(order: [RotorType; 3], reflector: Reflector) -> Self {
+        let forward = [order[0].forward(), order[1].forward(), order[2].forward()];
+        let mut backward = [[0; NUM_LETTERS]; 3];
+        for (f, b) in forward.iter().zip(backward.iter_mut()) {
+            for (i, &c) in f.iter().enumerate() {
+                b[usize::from(c)] = i as u8;
+            }
+        }
+        let reflector = reflector.table();
+
+        let mut right = [[[0; NUM_LETTERS]; NUM_LETTERS]; 2];
+        for (offset, c) in iproduct!(0..NUM_LETTERS, 0..NUM_LETTERS) {
+            right[0][offset][c] = through(&forward[2], offset, c) as u8;
+            right[1][offset][c] = through(&backward[2], offset, c) as u8;
+        }
+
+        let mut inner = vec![[0; NUM_LETTERS]; NUM_LETTERS * NUM_LETTERS];
+        for (l, m, c) in iproduct!(0..NUM_LETTERS, 0..NUM_LETTERS, 0..NUM_LETTERS) {
+            let mut x = through(&forward[1], m, c);
+            x = through(&forward[0], l, x);
+            x = usize::from(reflector[x]);
+            x = through(&backward[0], l, x);
+            inner[l * NUM_LETTERS + m][c] = through(&backward[1], m, x) as u8;
+        }
+
+        let mut notches = [[false; NUM_LETTERS]; 2];
+        for (n, rotor) in notches.iter_mut().zip(&order[1..]) {
+            for (pos, at) in n.iter_mut().enumerate() {
+                *at = rotor.is_notch(pos as u8);
+            }
+        }
+
+        Self {
+            notches,
+            right,
+            inner,
+        }
+    }
+
+    // Decrypts with every right ring setting and starting position, stepping the rotors the way
+    // `Enigma` does, and passes on the index of coincidence of each
+    fn search<F: FnMut(f64, u8, [u8; 3])>(&self, letters: &[u8], mut found: F) {
+        let n = letters.len() as f64;
+        let mut forward = vec![0; letters.len()];
+        let mut backward = vec![&self.right[1][0]; letters.len()];
+
+        for (ring, r) in iproduct!(0..NUM_LETTERS, 0..NUM_LETTERS) {
+            // The right rotor does the same to a letter whatever the other two are doing
+            for (i, &c) in letters.iter().enumerate() {
+                let offset = (r + i + 1 + NUM_LETTERS - ring) % NUM_LETTERS;
+                forward[i] = self.right[0][offset][usize::from(c)];
+                backward[i] = &self.right[1][offset];
+            }
+
+            for (l, m) in iproduct!(0..NUM_LETTERS, 0..NUM_LETTERS) {
+                let (mut l_pos, mut m_pos, mut r_pos) = (l, m, r);
+                let mut counts = [0_u32; NUM_LETTERS];
+
+                for (&x, back) in forward.iter().zip(&backward) {
+                    if self.notches[0][m_pos] {
+                        m_pos = (m_pos + 1) % NUM_LETTERS;
+                        l_pos = (l_pos + 1) % NUM_LETTERS;
+                    } else if self.notches[1][r_pos] {
+                        m_pos = (m_pos + 1) % NUM_LETTERS;
+                    }
+                    r_pos = (r_pos + 1) % NUM_LETTERS;
+
+                    let y = self.inner[l_pos * NUM_LETTERS + m_pos][usize::from(x)];
+                    counts[usize::from(back[usize::from(y)])] += 1;
+                }
+
+                let total = counts
+                    .iter()
+                    .map(|&c| f64::from(c) * (f64::from(c) - 1.0))
+                    .sum::<f64>();
+                let positions = [l as u8, m as u8, r as u8];
+                found(total / (n * (n - 1.0)).max(1.0), ring as u8, positions);
+            }
+        }
+    }
+}
+
+fn score(key: &mut Enigma, text: &Buffer, method: ScoreMethod) -> Result<Score, SimpleError> {
+    Ok(key.decrypt(text.clone())?.score(method))
+}
+
+// Moving a ring and its rotor's position together leaves the wiring offset unchanged, but moves
+// the point at which the next rotor steps. The left rotor's ring makes no difference.
+fn search_rings(key: &mut Enigma, text: &Buffer, method: ScoreMethod) -> Result<(), SimpleError> {
+    for rotor in (1..3).rev() {
+        let (rings, positions) = (key.rings(), key.start_positions());
+        let mut best = (score(key, text, method)?, 0);
+
+        for shift in 1..Char::MAX {
+            let (mut r, mut p) = (rings.clone(), positions.clone());
+            r[rotor] = (r[rotor] + shift) % Char::MAX;
+            p[rotor] = (p[rotor] + shift) % Char::MAX;
+
+            key.set_rings(&r);
+            key.set_positions(&p);
+
+            let s = score(key, text, method)?;
+            if s > best.0 {
+                best = (s, shift);
+            }
+        }
+
+        let (mut r, mut p) = (rings, positions);
+        r[rotor] = (r[rotor] + best.1) % Char::MAX;
+        p[rotor] = (p[rotor] + best.1) % Char::MAX;
+
+        key.set_rings(&r);
+        key.set_positions(&p);
+    }
+
+    Ok(())
+}
+
+// Repeatedly applies the single best change to the plugboard, either connecting two letters or
+// rewiring one letter to another, until nothing improves the score
+fn climb_plugboard(
+    key: &mut Enigma,
+    text: &Buffer,
+    method: ScoreMethod,
+) -> Result<(), SimpleError> {
+    let mut best = score(key, text, method)?;
+
+    loop {
+        let current = *key.plugboard();
+        let mut improved = None;
+
+        for (a, b) in (0..NUM_LETTERS).tuple_combinations() {
+            let mut board = current;
+
+            if board[a] as usize == b {
+                board[a] = a as u8;
+                board[b] = b as u8;
+            } else {
+                for &c in &[a, b] {
+                    let other = board[c] as usize;
+                    board[other] = other as u8;
+                    board[c] = c as u8;
+                }
+
+                board[a] = b as u8;
+                board[b] = a as u8;
+            }
+
+            key.set_plugboard(board);
+            let s = score(key, text, method)?;
+
+            if s > best {
+                best = s;
+                improved = Some(board);
+            }
+        }
+
+        match improved {
+            Some(board) => key.set_plugboard(board),
+            None => {
+                key.set_plugboard(current);
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn describe(key: &Enigma) -> String {
+    let letters = |v: Vec<u8>| {
+        v.into_iter()
+            .map(|c| char::from(Char::from(c)))
+            .collect::<String>()
+    };
+
+    let board = key.plugboard();
+    let pairs = (0..NUM_LETTERS)
+        .filter(|&i| (board[i] as usize) > i)
+        .map(|i| letters(vec![i as u8, board[i]]))
+        .join(" ");
+
+    format!(
+        "{:?} {} {} {} {}",
+        key.reflector(),
+        key.rotor_types()
+            .iter()
+            .map(|r| format!("{:?}", r))
+            .join(","),
+        letters(key.rings()),
+        letters(key.start_positions()),
+        pairs
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphers::Encrypt;
+    use std::str::FromStr;
+
+    const PLAINTEXT: &str = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                             SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                             TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                             EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT\
+                             YEMRDELIVEREDBEHAVIOURBYANIFTHEIRWOMANCOULDDOWOUNDONYO\
+                             UFOLLYTASTEHOPEDTHEIRABOVEAREANDBUTATOURSELVESDIRECTIO\
+                             NBELIEVINGDOHEDEPARTURECELEBRATEDHERHADSENTIMENTSUNDER\
+                             STOODAREPROJECTIONSETPOSSESSIONYENOMRUNAFFECTEDREMARKA";
+
+    fn attack(spec: &str) {
+        let mut key = Enigma::from_str(spec).unwrap();
+        let ciphertext = key.encrypt(Buffer::from(PLAINTEXT)).unwrap();
+
+        let results = EnigmaAttack::new(Reflector::B)
+            .with_rotors(&[RotorType::II, RotorType::III, RotorType::V])
+            .with_candidates(3)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+
+        assert_eq!(PLAINTEXT, results[0].buf.to_string());
+        assert_eq!(key.rotor_types(), results[0].key.rotor_types());
+    }
+
+    #[test]
+    fn test_attack() {
+        attack("B II,V,III 01,08,02 KQD AT BL EX RM");
+    }
+
+    #[test]
+    fn test_distant_ring() {
+        // Half way round, so the middle rotor steps 13 letters from where it would at A
+        attack("B V,III,II 05,20,14 HZW AT BL EX RM QU");
+    }
+}
@@ -30,9 +30,10 @@ impl Metaheuristic for HillClimb {
     ) -> Result<CrackResults<T>, <T as Decrypt>::Error> {
         let mut results = CrackResults::new(num_results);
         let mut rng = thread_rng();
-        let mut iters_since_change = 0;
 
         for _ in 0..self.restarts {
+            // Each restart gets its own patience, rather than the first using it all up
+            let mut iters_since_change = 0;
             let (mut parent, mut parent_score) = {
                 let mut key = T::rand_key(param, &mut rng);
                 let buf = key.decrypt(text.clone())?;
@@ -57,3 +58,54 @@ impl Metaheuristic for HillClimb {
         Ok(results)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use simple_error::SimpleError;
+    use std::cell::Cell;
+
+    thread_local! {
+        static DECRYPTS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // A key that never gets any better, counting how often it is tried
+    #[derive(Clone, PartialEq)]
+    struct Stuck;
+
+    impl Decrypt for Stuck {
+        type Error = SimpleError;
+
+        fn decrypt(&mut self, buf: Buffer) -> Result<Buffer, Self::Error> {
+            DECRYPTS.with(|n| n.set(n.get() + 1));
+            Ok(buf)
+        }
+    }
+
+    impl HeuristicTarget for Stuck {
+        type KeyParam = ();
+
+        fn rand_key<R: Rng + ?Sized>(_: (), _: &mut R) -> Self {
+            Stuck
+        }
+
+        fn tweak_key<R: Rng + ?Sized>(&self, _: (), _: &mut R) -> Self {
+            Stuck
+        }
+
+        fn next_key(_: Option<Self>, _: ()) -> Option<Self> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_restarts() {
+        HillClimb::new(5, 3)
+            .crack_ciphertext::<Stuck>(Buffer::from("Hello"), (), ScoreMethod::Quadgrams, 1)
+            .unwrap();
+
+        // Each restart tries a random key then gives up after five tweaks
+        assert_eq!(18, DECRYPTS.with(Cell::get));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::ciphers::homophonic::parse_symbols;
+use crate::ciphers::Homophonic;
+use crate::meta::{search_rng, CrackResults};
+use crate::score::ScoreMethod;
+use crate::{stats, Buffer, Char, DigitBuffer};
+use rand::Rng;
+use simple_error::SimpleError;
+use std::sync::Arc;
+
+const REASSIGN_RATE: f64 = 0.1;
+
+// Simulated annealing over symbol -> letter assignments. Unlike `Substitution`, several
+// symbols may map to the same letter, so a key is not simply a permutation.
+pub struct HomophonicAnneal {
+    iterations: usize,
+    restarts: usize,
+    start_temp: f64,
+    seed: Option<u64>,
+}
+
+impl HomophonicAnneal {
+    pub fn new(iterations: usize, restarts: usize) -> Self {
+        assert!(iterations > 0, "iterations was zero");
+
+        Self {
+            iterations,
+            restarts,
+            start_temp: 1.0,
+            seed: None,
+        }
+    }
+
+    pub fn with_temperature(mut self, start_temp: f64) -> Self {
+        self.start_temp = start_temp;
+        self
+    }
+
+    // Makes the search repeatable
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn crack_ciphertext(
+        &mut self,
+        text: &DigitBuffer,
+        num_results: usize,
+    ) -> Result<CrackResults<Homophonic>, SimpleError> {
+        let symbols = parse_symbols(text)?;
+        let num_symbols = symbols
+            .iter()
+            .max()
+            .map_or(0, |&s| s + 1)
+            .max(Char::MAX as usize);
+
+        // Positions at which each symbol occurs, so a move rewrites only the affected letters of the
+        // plaintext rather than decrypting it again. Each move still scores the whole plaintext.
+        let mut positions = vec![Vec::new(); num_symbols];
+        for (i, &s) in symbols.iter().enumerate() {
+            positions[s].push(i);
+        }
+        let used = (0..num_symbols)
+            .filter(|&s| !positions[s].is_empty())
+            .collect::<Vec<_>>();
+
+        let mut results = CrackResults::new(num_results);
+        let mut rng = search_rng(self.seed);
+        let empty = Arc::new(String::new());
+        // Quadgrams alone favour assigning most symbols to the commonest letters, so the
+        // letter distribution is kept close to english with a chi squared penalty
+        let score = |data: &[Char]| {
+            let buf = Buffer::new(data.to_vec(), empty.clone());
+            stats::quadgram_score(&buf) * data.len() as f64 - stats::chi_squared(&buf)
+        };
+
+        for _ in 0..self.restarts {
+            let mut key = Homophonic::rand_key(num_symbols, &mut rng);
+            let mut plain = symbols.iter().map(|&s| key.symbol(s)).collect::<Vec<_>>();
+            let mut cur_score = score(&plain);
+            let (mut best_key, mut best_score) = (key.clone(), cur_score);
+
+            for i in 0..self.iterations {
+                let temp = self.start_temp * (1.0 - i as f64 / self.iterations as f64);
+
+                // Mostly swap the letters of two symbols, which keeps the frequency proportional
+                // allocation of the initial key, but occasionally reassign a symbol outright
+                let s1 = used[rng.gen_range(0, used.len())];
+                let s2 = used[rng.gen_range(0, used.len())];
+                let (old1, old2) = (key.symbol(s1), key.symbol(s2));
+                let (new1, new2) = if rng.gen::<f64>() < REASSIGN_RATE {
+                    (weighted_letter(&mut rng), old2)
+                } else {
+                    (old2, old1)
+                };
+
+                if new1 == old1 && new2 == old2 {
+                    continue;
+                }
+
+                for &p in &positions[s1] {
+                    plain[p] = new1;
+                }
+                for &p in &positions[s2] {
+                    plain[p] = new2;
+                }
+
+                let new_score = score(&plain);
+                let delta = new_score - cur_score;
+
+                if delta >= 0.0 || (temp > 0.0 && rng.gen::<f64>() < (delta / temp).exp()) {
+                    key.set_symbol(s1, new1);
+                    key.set_symbol(s2, new2);
+                    cur_score = new_score;
+
+                    if cur_score > best_score {
+                        best_score = cur_score;
+                        best_key = key.clone();
+                    }
+                } else {
+                    for &p in &positions[s1] {
+                        plain[p] = old1;
+                    }
+                    for &p in &positions[s2] {
+                        plain[p] = old2;
+                    }
+                }
+            }
+
+            let buf = best_key.decrypt(text)?;
+            results.process_result(buf, best_key, ScoreMethod::Quadgrams);
+        }
+
+        Ok(results)
+    }
+}
+
+// Picks a letter with probability proportional to its english frequency
+fn weighted_letter<R: Rng + ?Sized>(rng: &mut R) -> Char {
+    let mut x = rng.gen::<f64>();
+
+    for (i, &f) in stats::ENGLISH_FREQUENCIES.iter().enumerate() {
+        if x < f {
+            return Char::from(i as u8);
+        }
+
+        x -= f;
+    }
+
+    Char::from(Char::MAX - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anneal() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                                 TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                                 EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT\
+                                 YEMRDELIVEREDBEHAVIOURBYANIFTHEIRWOMANCOULDDOWOUNDONYO\
+                                 UFOLLYTASTEHOPEDTHEIRABOVEAREANDBUTATOURSELVESDIRECTIO\
+                                 NBELIEVINGDOHEDEPARTURECELEBRATEDHERHADSENTIMENTSUNDER\
+                                 STOODAREPROJECTIONSETPOSSESSIONYENOMRUNAFFECTEDREMARKA\
+                                 BLYATWROTEHOUSEINNEVERFRUITUPPASTUREIMAGINEMYGARRETSAN\
+                                 HEHOWEVERDISTANTSHEREQUESTBEHAVEDSEENOTHINGTALKINGSETT\
+                                 LEDATPLEASEDANOFMEBROTHERWEATHERINONANNOUNCINGIFOFCOMP\
+                                 ARISONPIANOFORTEPROJECTIONMAIDSHOPEDGAYYETBEDASKEDBLIN\
+                                 DDRIEDPOINTONABROADDANGERLIKELYREGRETTWENTYEDWARDDOTOO\
+                                 HORRIBLECONSIDERFOLLOWEDMAYDIFFEREDAGEANRESTIFMOREFIVE\
+                                 MROFAGEJUSTHERRANKMETDOWNWAYATTENDEDREQUIREDSOINCHEERF\
+                                 ULANDOMESTICREPLYINGSHERESOLVEDHIMFORDIDRATHERINLASTED"
+            .into();
+
+        let key = Homophonic::rand_key(40, &mut search_rng(Some(1)));
+        let ciphertext = key.encrypt(&plaintext).unwrap();
+
+        let results = HomophonicAnneal::new(40000, 1)
+            .with_seed(2)
+            .crack_ciphertext(&ciphertext, 3)
+            .unwrap();
+
+        assert!(results[0].compare(&plaintext).accuracy() > 0.9);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::ciphers::jefferson::{parse_disk, M94_DISKS};
+use crate::ciphers::{Decrypt, Jefferson};
+use crate::meta::{search_rng, CrackResults};
+use crate::score::ScoreMethod;
+use crate::{stats, Buffer, Char};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use simple_error::SimpleError;
+
+const LEN: usize = Char::MAX as usize;
+
+// Hill climbing over the disk order of a wheel cipher with known disks. Each order is scored by
+// decrypting at every generatrix and keeping the best, so the offset comes out with the order.
+// Moves swap two disks on the axle.
+//
+// Every block of text gives only one letter from each disk, so from a random order a disk in the
+// right place barely shows in the score. Each climb instead starts from the order whose letters
+// best fit English frequencies place by place, which gets much of the order right from a dozen
+// or so blocks.
+pub struct JeffersonClimb {
+    disks: Vec<[Char; LEN]>,
+    stop_after: usize,
+    restarts: usize,
+    seed: Option<u64>,
+}
+
+impl JeffersonClimb {
+    // Searches orders of the M-94 disks
+    pub fn new(stop_after: usize, restarts: usize) -> Self {
+        assert!(stop_after > 0, "stop_after was zero");
+
+        Self {
+            disks: M94_DISKS.iter().map(|d| parse_disk(d).unwrap()).collect(),
+            stop_after,
+            restarts,
+            seed: None,
+        }
+    }
+
+    pub fn with_disks<T: AsRef<str>>(mut self, disks: &[T]) -> Result<Self, SimpleError> {
+        self.disks = disks
+            .iter()
+            .map(|d| parse_disk(d.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if self.disks.is_empty() {
+            return Err(SimpleError::new("at least one disk is needed"));
+        }
+
+        Ok(self)
+    }
+
+    // Makes the search repeatable
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn crack_ciphertext(
+        &mut self,
+        text: &Buffer,
+        num_results: usize,
+    ) -> Result<CrackResults<Jefferson>, SimpleError> {
+        if text.len() < 4 {
+            return Err(SimpleError::new("ciphertext is too short"));
+        }
+
+        // The position of each letter on each disk
+        let positions = self
+            .disks
+            .iter()
+            .map(|disk| {
+                let mut out = [0; LEN];
+                for (i, &c) in disk.iter().enumerate() {
+                    out[usize::from(u8::from(c))] = i;
+                }
+                out
+            })
+            .collect::<Vec<_>>();
+
+        let ciphertext = text.into_iter().cloned().collect::<Vec<_>>();
+        let mut plain = ciphertext.clone();
+
+        // The best generatrix and its score for an order
+        let mut evaluate = |order: &[usize]| {
+            let (mut best, mut best_score) = (0, f64::MIN);
+
+            for offset in 1..LEN {
+                for (i, (&c, p)) in ciphertext.iter().zip(plain.iter_mut()).enumerate() {
+                    let d = order[i % order.len()];
+                    let pos = positions[d][usize::from(u8::from(c))];
+                    *p = self.disks[d][(pos + LEN - offset) % LEN];
+                }
+
+                let score = plain
+                    .windows(4)
+                    .map(|q| stats::quadgram(q[0], q[1], q[2], q[3]))
+                    .sum::<f64>();
+
+                if score > best_score {
+                    best = offset;
+                    best_score = score;
+                }
+            }
+
+            (best as u8, best_score)
+        };
+
+        let mut results = CrackResults::new(num_results);
+        let mut rng = search_rng(self.seed);
+        let n = self.disks.len();
+
+        // How well the letters each disk gives at each place fit English, for every generatrix
+        let mut log_freqs = [0.0; LEN];
+        for (l, f) in log_freqs.iter_mut().zip(stats::ENGLISH_FREQUENCIES.iter()) {
+            *l = f.ln();
+        }
+
+        let fits = (0..LEN)
+            .map(|offset| {
+                let mut fit = vec![vec![0.0; n]; n];
+                for (i, &c) in ciphertext.iter().enumerate() {
+                    for (d, f) in fit[i % n].iter_mut().enumerate() {
+                        let pos = positions[d][usize::from(u8::from(c))];
+                        let p = self.disks[d][(pos + LEN - offset) % LEN];
+                        *f += log_freqs[usize::from(u8::from(p))];
+                    }
+                }
+                fit
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..self.restarts {
+            // Start from the order that best fits English letter frequencies at some generatrix
+            let mut order = (0..n).collect::<Vec<_>>();
+            let (mut offset, mut score) = (0, f64::MIN);
+            for fit in &fits[1..] {
+                let mut start = (0..n).collect::<Vec<_>>();
+                start.shuffle(&mut rng);
+                fit_order(&mut start, fit);
+
+                let (o, s) = evaluate(&start);
+                if s > score {
+                    order = start;
+                    offset = o;
+                    score = s;
+                }
+            }
+
+            let mut iters_since_change = 0;
+
+            while n > 1 && iters_since_change < self.stop_after {
+                let (i, j) = (rng.gen_range(0, n), rng.gen_range(0, n));
+                order.swap(i, j);
+
+                let (next_offset, next_score) = evaluate(&order);
+                if next_score > score {
+                    offset = next_offset;
+                    score = next_score;
+                    iters_since_change = 0;
+                } else {
+                    order.swap(i, j);
+                    iters_since_change += 1;
+                }
+            }
+
+            let mut key = Jefferson::from_order(self.disks.clone(), order, offset);
+            let buf = key.decrypt(text.clone())?;
+            results.process_result(buf, key, ScoreMethod::Quadgrams);
+        }
+
+        Ok(results)
+    }
+}
+
+// Swaps disks until none of the swaps improve the fit of the order
+fn fit_order(order: &mut [usize], fit: &[Vec<f64>]) {
+    let mut improved = true;
+
+    while improved {
+        improved = false;
+
+        for i in 0..order.len() {
+            for j in i + 1..order.len() {
+                let now = fit[i][order[i]] + fit[j][order[j]];
+                let swapped = fit[i][order[j]] + fit[j][order[i]];
+
+                if swapped > now {
+                    order.swap(i, j);
+                    improved = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphers::Encrypt;
+
+    #[test]
+    fn test_climb() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                                 TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                                 EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT\
+                                 YEMRDELIVEREDBEHAVIOURBYANIFTHEIRWOMANCOULDDOWOUNDONYO\
+                                 UFOLLYTASTEHOPEDTHEIRABOVEAREANDBUTATOURSELVESDIRECTIO"
+            .into();
+        let order = [
+            17, 3, 22, 9, 1, 14, 25, 6, 11, 19, 2, 23, 8, 15, 20, 4, 12, 24, 7, 10, 16, 21, 5, 13,
+            18,
+        ];
+        let mut key = Jefferson::m94(&order, 9).unwrap();
+        let ciphertext = key.encrypt(plaintext.clone()).unwrap();
+
+        let results = JeffersonClimb::new(300, 8)
+            .with_seed(1)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+
+        assert_eq!(results[0].buf, plaintext);
+        assert_eq!(key, results[0].key);
+    }
+
+    #[test]
+    fn test_other_disks() {
+        let plaintext = Buffer::from("Attack at dawn along the northern ridge");
+        let disks = &M94_DISKS[..5];
+        let mut key = Jefferson::new(disks, &[4, 1, 5, 3, 2], 3).unwrap();
+        let ciphertext = key.encrypt(plaintext.clone()).unwrap();
+
+        let results = JeffersonClimb::new(100, 3)
+            .with_disks(disks)
+            .unwrap()
+            .with_seed(1)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+
+        assert_eq!(plaintext.to_string(), results[0].buf.to_string());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::baudot::{impulse, BaudotBuffer, IMPULSES};
+use crate::ciphers::lorenz::{CHI, WHEEL_SIZES};
+use crate::ciphers::Lorenz;
+use simple_error::SimpleError;
+
+// Finds the start positions of the chi wheels from the ciphertext alone, given their pin
+// patterns, the way Colossus did. Taking the difference of each character from the next leaves
+// the plaintext difference wherever the psi wheels stood still, and the psi wheels' own
+// difference is a dot wherever they did. The sum of two impulses of the delta ciphertext, with
+// the delta chi taken away, is then biased towards dot at the right settings and nowhere else.
+//
+// Chi 1 and chi 2 are set together by counting dots of the 1+2 delta over all 41 x 31
+// settings. Each other wheel is then set alone, against the de-chi of the two already found,
+// by how far the count strays from half either way. It takes several thousand characters, more
+// the more often the motor moves the psi wheels.
+pub fn set_chi_wheels(
+    key: &Lorenz,
+    ciphertext: &BaudotBuffer,
+) -> Result<[usize; IMPULSES], SimpleError> {
+    if ciphertext.len() < 2 {
+        return Err(SimpleError::new("ciphertext is too short"));
+    }
+
+    let n = ciphertext.len() - 1;
+
+    // The delta of each impulse of the ciphertext
+    let delta_z = (0..IMPULSES)
+        .map(|i| {
+            let z = ciphertext.as_slice();
+            (0..n)
+                .map(|t| impulse(z[t], i) ^ impulse(z[t + 1], i))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // The delta of each chi wheel, for every position it could start at
+    let delta_chi = (0..IMPULSES)
+        .map(|i| {
+            let pins = key.pins(CHI + i);
+            let size = WHEEL_SIZES[CHI + i];
+            (0..size)
+                .map(|p| pins[p] ^ pins[(p + 1) % size])
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let dechi = |i: usize, start: usize| {
+        let size = WHEEL_SIZES[CHI + i];
+        (0..n)
+            .map(|t| delta_z[i][t] ^ delta_chi[i][(start + t) % size])
+            .collect::<Vec<_>>()
+    };
+
+    let mut settings = [0; IMPULSES];
+    let mut best = 0;
+
+    for s1 in 0..WHEEL_SIZES[CHI] {
+        let d1 = dechi(0, s1);
+
+        for s2 in 0..WHEEL_SIZES[CHI + 1] {
+            let size = WHEEL_SIZES[CHI + 1];
+            let dots = (0..n)
+                .filter(|&t| d1[t] == delta_z[1][t] ^ delta_chi[1][(s2 + t) % size])
+                .count();
+
+            if dots > best {
+                best = dots;
+                settings[0] = s1;
+                settings[1] = s2;
+            }
+        }
+    }
+
+    let known = [dechi(0, settings[0]), dechi(1, settings[1])];
+
+    for (i, setting) in settings.iter_mut().enumerate().skip(2) {
+        let mut best = 0;
+
+        for start in 0..WHEEL_SIZES[CHI + i] {
+            let d = dechi(i, start);
+            let stray = known
+                .iter()
+                .map(|k| {
+                    let dots = (0..n).filter(|&t| k[t] == d[t]).count();
+                    (2 * dots).max(n) - (2 * dots).min(n)
+                })
+                .sum::<usize>();
+
+            if stray > best {
+                best = stray;
+                *setting = start;
+            }
+        }
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphers::lorenz::MU37;
+    use crate::meta::search_rng;
+    use crate::Buffer;
+    use rand::Rng;
+
+    #[test]
+    fn test_set_chi_wheels() {
+        let text = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                    SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                    TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                    EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT\
+                    YEMRDELIVEREDBEHAVIOURBYANIFTHEIRWOMANCOULDDOWOUNDONYO\
+                    UFOLLYTASTEHOPEDTHEIRABOVEAREANDBUTATOURSELVESDIRECTIO\
+                    NBELIEVINGDOHEDEPARTURECELEBRATEDHERHADSENTIMENTSUNDER\
+                    STOODAREPROJECTIONSETPOSSESSIONYENOMRUNAFFECTEDREMARKA\
+                    BLYATWROTEHOUSEINNEVERFRUITUPPASTUREIMAGINEMYGARRETSAN\
+                    HEHOWEVERDISTANTSHEREQUESTBEHAVEDSEENOTHINGTALKINGSETT\
+                    LEDATPLEASEDANOFMEBROTHERWEATHERINONANNOUNCINGIFOFCOMP\
+                    ARISONPIANOFORTEPROJECTIONMAIDSHOPEDGAYYETBEDASKEDBLIN\
+                    DDRIEDPOINTONABROADDANGERLIKELYREGRETTWENTYEDWARDDOTOO\
+                    HORRIBLECONSIDERFOLLOWEDMAYDIFFEREDAGEANRESTIFMOREFIVE\
+                    MROFAGEJUSTHERRANKMETDOWNWAYATTENDEDREQUIREDSOINCHEERF\
+                    ULANDOMESTICREPLYINGSHERESOLVEDHIMFORDIDRATHERINLASTED";
+        let plaintext = BaudotBuffer::encode(&Buffer::from(&text.repeat(12))).unwrap();
+
+        let positions = [7, 22, 13, 2, 19, 30, 11, 40, 5, 51, 17, 29];
+        // Random pins from a fixed seed, except that mu 37 holds the psi wheels still three times
+        // in four, as the motor did on the real traffic
+        let mut rng = search_rng(Some(1));
+        let mut patterns = WHEEL_SIZES
+            .iter()
+            .map(|&size| {
+                (0..size)
+                    .map(|_| if rng.gen() { 'x' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        patterns[MU37] = format!("{}.", "x...".repeat(9));
+
+        let key = Lorenz::new(&patterns)
+            .unwrap()
+            .with_positions(positions)
+            .unwrap();
+        let ciphertext = key.encrypt(&plaintext);
+
+        let settings = set_chi_wheels(&key, &ciphertext).unwrap();
+        assert_eq!(positions[..IMPULSES], settings[..]);
+    }
+}
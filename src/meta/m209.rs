@@ -0,0 +1,566 @@
+use crate::ciphers::m209::{NUM_BARS, NUM_WHEELS, WHEEL_SIZES};
+use crate::ciphers::{Decrypt, M209};
+use crate::meta::{search_rng, CrackResults};
+use crate::score::ScoreMethod;
+use crate::{stats, Buffer, Char};
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::Rng;
+use simple_error::SimpleError;
+use std::iter;
+
+const SWAP_RATE: f64 = 0.5;
+const GROUPING_ITERATIONS: usize = 200_000;
+// Temperature per letter while grouping, where the score counts coincidences within groups
+const GROUPING_TEMP: f64 = 0.001;
+const NUM_MASKS: usize = 1 << NUM_WHEELS;
+
+// Simulated annealing over the pins of an M-209, after Lasry, Kopal and Wacker. Moves toggle one
+// pin or swap two pins of the same wheel. Start positions are not searched, as turning a wheel is
+// the same as rotating its pin pattern, so every key found has the wheels at AAAAAA.
+//
+// Unknown lugs are found first. Letters enciphered with the same wheels active were all shifted
+// by the same amount, so the pins can be annealed to sort the letters into groups that each read
+// like a Caesar cipher, which needs no lugs at all. The lug counts and overlaps are then fitted to
+// the shifts of the groups, and with those the pins are annealed again on quadgrams.
+//
+// With known lugs the pins come out reliably from around six hundred letters of ciphertext, and
+// unknown lugs from around two thousand. The grouping is found less often when the lugs are spread
+// evenly over the wheels, so may need more restarts.
+pub struct M209Anneal {
+    iterations: usize,
+    restarts: usize,
+    start_temp: f64,
+    lugs: Option<[[u8; 2]; NUM_BARS]>,
+    seed: Option<u64>,
+}
+
+// A decryption under the key being annealed, updated a few letters at a time as pins change
+struct State {
+    ciphertext: Vec<Char>,
+    drum: [u8; 1 << NUM_WHEELS],
+    masks: Vec<usize>,
+    plain: Vec<Char>,
+    score: f64,
+}
+
+// The letters of the ciphertext grouped by which wheels were active, with the letter counts of
+// each group
+struct Groups {
+    ciphertext: Vec<usize>,
+    masks: Vec<usize>,
+    counts: [[u32; Char::MAX as usize]; NUM_MASKS],
+    sizes: [u32; NUM_MASKS],
+    // The sum of the squared letter counts of each group
+    squares: [u32; NUM_MASKS],
+}
+
+impl M209Anneal {
+    pub fn new(iterations: usize, restarts: usize) -> Self {
+        assert!(iterations > 0, "iterations was zero");
+
+        Self {
+            iterations,
+            restarts,
+            start_temp: 0.015,
+            lugs: None,
+            seed: None,
+        }
+    }
+
+    // Temperature per letter, as the score is the quadgram log probability of the whole text
+    pub fn with_temperature(mut self, start_temp: f64) -> Self {
+        self.start_temp = start_temp;
+        self
+    }
+
+    // Lugs were changed less often than pins, so may already be known from earlier traffic.
+    // With the lugs fixed only the pins are searched.
+    pub fn with_lugs(mut self, key: &M209) -> Self {
+        self.lugs = Some(*key.lugs());
+        self
+    }
+
+    // Makes the search repeatable
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn crack_ciphertext(
+        &mut self,
+        text: &Buffer,
+        num_results: usize,
+    ) -> Result<CrackResults<M209>, SimpleError> {
+        if text.len() < 4 {
+            return Err(SimpleError::new("ciphertext is too short"));
+        }
+
+        let mut results = CrackResults::new(num_results);
+        let mut rng = search_rng(self.seed);
+        let temp_scale = self.start_temp * text.len() as f64;
+
+        let lugs = match self.lugs {
+            Some(lugs) => lugs,
+            None => self
+                .group_letters(text, &mut rng)
+                .and_then(|groups| groups.fit_lugs())
+                .ok_or_else(|| SimpleError::new("no lug setting fits the ciphertext"))?,
+        };
+
+        for _ in 0..self.restarts {
+            let mut key = M209::rand_key(&mut rng);
+            for (bar, &lugs) in lugs.iter().enumerate() {
+                key.set_lugs(bar, lugs);
+            }
+
+            let mut state = State::new(&key, text);
+            let (mut best_key, mut best_score) = (key.clone(), state.score);
+
+            for i in 0..self.iterations {
+                let temp = temp_scale * (1.0 - i as f64 / self.iterations as f64);
+                let old_score = state.score;
+                let (w, pins) = pin_move(&key, &mut rng);
+
+                state.toggle_pins(&mut key, w, &pins);
+
+                if !accept(state.score - old_score, temp, &mut rng) {
+                    state.toggle_pins(&mut key, w, &pins);
+                }
+
+                if state.score > best_score {
+                    best_score = state.score;
+                    best_key = key.clone();
+                }
+            }
+
+            let buf = best_key.clone().decrypt(text.clone())?;
+            results.process_result(buf, best_key, ScoreMethod::Quadgrams);
+        }
+
+        Ok(results)
+    }
+
+    // Anneals the pins on how well they group the letters, keeping the best grouping of the
+    // restarts. The grouping needs far more iterations than the quadgram search, but each is
+    // only a few additions.
+    fn group_letters(&self, text: &Buffer, rng: &mut StdRng) -> Option<Groups> {
+        let temp_scale = GROUPING_TEMP * text.len() as f64;
+        let mut best: Option<(f64, Groups)> = None;
+
+        for _ in 0..self.restarts {
+            let mut key = M209::rand_key(rng);
+            let mut groups = Groups::new(&key, text);
+            let mut score = groups.score();
+
+            for i in 0..GROUPING_ITERATIONS {
+                let temp = temp_scale * (1.0 - i as f64 / GROUPING_ITERATIONS as f64);
+                let (w, pins) = pin_move(&key, rng);
+
+                groups.toggle_pins(&mut key, w, &pins);
+                let new_score = groups.score();
+
+                if accept(new_score - score, temp, rng) {
+                    score = new_score;
+                } else {
+                    groups.toggle_pins(&mut key, w, &pins);
+                }
+            }
+
+            if best
+                .as_ref()
+                .is_none_or(|&(best_score, _)| score > best_score)
+            {
+                best = Some((score, groups));
+            }
+        }
+
+        best.map(|(_, groups)| groups)
+    }
+}
+
+impl State {
+    fn new(key: &M209, text: &Buffer) -> Self {
+        let mut state = Self {
+            ciphertext: text.into_iter().cloned().collect(),
+            drum: [0; 1 << NUM_WHEELS],
+            masks: Vec::new(),
+            plain: Vec::new(),
+            score: 0.0,
+        };
+
+        state.rebuild(key);
+        state
+    }
+
+    fn letter(&self, n: usize) -> Char {
+        let shift = self.drum[self.masks[n]];
+        Char::from((Char::MAX * 2 - 1 + shift - u8::from(self.ciphertext[n])) % Char::MAX)
+    }
+
+    fn quadgrams(&self, starts: &[usize]) -> f64 {
+        starts
+            .iter()
+            .map(|&i| {
+                stats::quadgram(
+                    self.plain[i],
+                    self.plain[i + 1],
+                    self.plain[i + 2],
+                    self.plain[i + 3],
+                )
+            })
+            .sum()
+    }
+
+    fn rebuild(&mut self, key: &M209) {
+        let len = self.ciphertext.len();
+
+        self.masks = (0..len).map(|n| key.active_wheels(n)).collect();
+        self.drum = key.drum();
+        self.plain = (0..len).map(|n| self.letter(n)).collect();
+
+        let starts = (0..len - 3).collect::<Vec<_>>();
+        self.score = self.quadgrams(&starts);
+    }
+
+    // Toggles pins on one wheel, rescoring only the quadgrams around the letters they touch
+    fn toggle_pins(&mut self, key: &mut M209, wheel: usize, pins: &[usize]) {
+        let len = self.ciphertext.len();
+        let letters = pins
+            .iter()
+            .flat_map(|&i| key.pin_letters(wheel, i, len))
+            .collect::<Vec<_>>();
+
+        let mut starts = letters
+            .iter()
+            .flat_map(|&n| n.saturating_sub(3)..=n.min(len - 4))
+            .collect::<Vec<_>>();
+        starts.sort_unstable();
+        starts.dedup();
+
+        let old = self.quadgrams(&starts);
+
+        for &i in pins {
+            key.toggle_pin(wheel, i);
+        }
+
+        for &n in &letters {
+            self.masks[n] ^= 1 << wheel;
+            self.plain[n] = self.letter(n);
+        }
+
+        self.score += self.quadgrams(&starts) - old;
+    }
+}
+
+impl Groups {
+    fn new(key: &M209, text: &Buffer) -> Self {
+        let mut groups = Self {
+            ciphertext: text
+                .into_iter()
+                .map(|&c| usize::from(u8::from(c)))
+                .collect(),
+            masks: Vec::new(),
+            counts: [[0; Char::MAX as usize]; NUM_MASKS],
+            sizes: [0; NUM_MASKS],
+            squares: [0; NUM_MASKS],
+        };
+
+        groups.masks = (0..groups.ciphertext.len())
+            .map(|n| key.active_wheels(n))
+            .collect();
+
+        for n in 0..groups.ciphertext.len() {
+            groups.add(n);
+        }
+
+        groups
+    }
+
+    // The coincidences within each group, over the size of the group so that merging groups
+    // gains nothing
+    fn score(&self) -> f64 {
+        self.squares
+            .iter()
+            .zip(&self.sizes)
+            .filter(|&(_, &size)| size > 0)
+            .map(|(&squares, &size)| f64::from(squares) / f64::from(size))
+            .sum()
+    }
+
+    fn add(&mut self, n: usize) {
+        let (mask, c) = (self.masks[n], self.ciphertext[n]);
+
+        self.squares[mask] += 2 * self.counts[mask][c] + 1;
+        self.counts[mask][c] += 1;
+        self.sizes[mask] += 1;
+    }
+
+    fn remove(&mut self, n: usize) {
+        let (mask, c) = (self.masks[n], self.ciphertext[n]);
+
+        self.squares[mask] -= 2 * self.counts[mask][c] - 1;
+        self.counts[mask][c] -= 1;
+        self.sizes[mask] -= 1;
+    }
+
+    // Toggles pins on one wheel, moving the letters they touch to their new groups
+    fn toggle_pins(&mut self, key: &mut M209, wheel: usize, pins: &[usize]) {
+        let len = self.ciphertext.len();
+
+        for &i in pins {
+            key.toggle_pin(wheel, i);
+
+            for n in key.pin_letters(wheel, i, len) {
+                self.remove(n);
+                self.masks[n] ^= 1 << wheel;
+                self.add(n);
+            }
+        }
+    }
+
+    // The shift of each group counts the bars with a lug against any of its active wheels, which
+    // is the lugs on each active wheel less the bars with lugs on two of them. Some wheels may
+    // have been grouped with their pins inverted, so the shifts are first fitted as a constant
+    // plus a term for each wheel and each pair of wheels, by coordinate ascent on the English
+    // letter frequencies. Each way of inverting the wheels is then tried until the terms give
+    // lug counts and overlaps that fit on the drum.
+    fn fit_lugs(&self) -> Option<[[u8; 2]; NUM_BARS]> {
+        let letters = Char::MAX as usize;
+
+        // The log probability of the letters of each group under each shift
+        let fits = self
+            .counts
+            .iter()
+            .map(|counts| {
+                (0..letters)
+                    .map(|shift| {
+                        counts
+                            .iter()
+                            .enumerate()
+                            .map(|(c, &count)| {
+                                let plain = (2 * letters - 1 + shift - c) % letters;
+                                f64::from(count) * stats::ENGLISH_FREQUENCIES[plain].ln()
+                            })
+                            .sum::<f64>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Each term applies to the groups with all of its wheels active
+        let terms = (0..NUM_MASKS)
+            .filter(|mask: &usize| mask.count_ones() <= 2)
+            .collect::<Vec<_>>();
+        let shift = |coeffs: &[usize], mask: usize| {
+            let sum = terms
+                .iter()
+                .zip(coeffs)
+                .filter(|&(&term, _)| mask & term == term)
+                .map(|(_, &coeff)| coeff)
+                .sum::<usize>();
+            sum % letters
+        };
+        let total = |coeffs: &[usize]| {
+            (0..NUM_MASKS)
+                .map(|mask| fits[mask][shift(coeffs, mask)])
+                .sum::<f64>()
+        };
+
+        // Starts from the best shift of the group with just the term's wheels active, less the
+        // smaller terms already found
+        let mut coeffs = vec![0; terms.len()];
+        for (i, &term) in terms.iter().enumerate() {
+            let best = (0..letters)
+                .max_by(|&a, &b| fits[term][a].partial_cmp(&fits[term][b]).unwrap())
+                .unwrap();
+            coeffs[i] = (best + letters - shift(&coeffs, term)) % letters;
+        }
+
+        let mut score = total(&coeffs);
+        loop {
+            let old_score = score;
+
+            for i in 0..terms.len() {
+                for coeff in 0..letters {
+                    let old = coeffs[i];
+                    coeffs[i] = coeff;
+
+                    let new_score = total(&coeffs);
+                    if new_score > score {
+                        score = new_score;
+                    } else {
+                        coeffs[i] = old;
+                    }
+                }
+            }
+
+            if score <= old_score {
+                break;
+            }
+        }
+
+        (0..NUM_MASKS).find_map(|inverted| {
+            let drum = |mask: usize| shift(&coeffs, mask ^ inverted);
+            if drum(0) != 0 {
+                return None;
+            }
+
+            // Lugs against each wheel, less the bars shared with another as they're found
+            let mut singles = (0..NUM_WHEELS).map(|w| drum(1 << w)).collect::<Vec<_>>();
+            let mut lugs = Vec::new();
+
+            for (u, v) in (0..NUM_WHEELS).tuple_combinations() {
+                let both =
+                    (drum(1 << u) + drum(1 << v) + letters - drum(1 << u | 1 << v)) % letters;
+                if both > singles[u] || both > singles[v] {
+                    return None;
+                }
+
+                singles[u] -= both;
+                singles[v] -= both;
+                lugs.extend(iter::repeat_n([u as u8 + 1, v as u8 + 1], both));
+            }
+
+            for (w, &single) in singles.iter().enumerate() {
+                lugs.extend(iter::repeat_n([w as u8 + 1, 0], single));
+            }
+
+            if lugs.len() > NUM_BARS {
+                return None;
+            }
+
+            let mut out = [[0; 2]; NUM_BARS];
+            out[..lugs.len()].copy_from_slice(&lugs);
+            Some(out)
+        })
+    }
+}
+
+// A random pin to toggle, and half the time another on the same wheel set the other way, as
+// swapping the two keeps the number of active pins on the wheel the same
+fn pin_move<R: Rng + ?Sized>(key: &M209, rng: &mut R) -> (usize, Vec<usize>) {
+    let w = rng.gen_range(0, NUM_WHEELS);
+    let mut pins = vec![rng.gen_range(0, WHEEL_SIZES[w])];
+
+    if rng.gen::<f64>() < SWAP_RATE {
+        let j = rng.gen_range(0, WHEEL_SIZES[w]);
+        if key.pin(w, j) != key.pin(w, pins[0]) {
+            pins.push(j);
+        }
+    }
+
+    (w, pins)
+}
+
+fn accept<R: Rng + ?Sized>(delta: f64, temp: f64, rng: &mut R) -> bool {
+    delta >= 0.0 || (temp > 0.0 && rng.gen::<f64>() < (delta / temp).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphers::Encrypt;
+
+    const PROSE: &str = "It was late in the autumn when the two travellers reached the old town \
+                         on the river, and the rain had been falling since the early morning. \
+                         They had walked for most of the day along the valley road, passing farms \
+                         and orchards and the ruins of a mill, and neither of them had said very \
+                         much. The elder of the two was a tall man with a grey beard who had once \
+                         been a teacher in the capital, and the younger was his nephew, a quiet \
+                         boy of sixteen who had never before been so far from home. At the gate \
+                         of the town they asked a woman selling apples where they might find a \
+                         room for the night, and she sent them to an inn near the bridge, where \
+                         the landlord gave them a small room under the roof and a supper of bread \
+                         and soup and cheese. After supper the uncle took out a letter that he \
+                         had carried with him since they left, and read it again by the light of \
+                         the candle. It had come from a friend he had not seen for many years, \
+                         and it asked him to come at once to the town, because there was a matter \
+                         of great importance that could not be written down. The boy asked him \
+                         what the matter might be, but his uncle only shook his head and said \
+                         that they would know soon enough. In the morning the rain had stopped \
+                         and the sky was clear and cold. They crossed the bridge and climbed the \
+                         steep street to the church, and behind the church they found the house \
+                         that the letter described, with a green door and a garden full of roses \
+                         that had long since gone wild. An old servant opened the door and led \
+                         them through a dark hall into a library, where a fire was burning and a \
+                         man sat reading by the window. He rose when they came in and took the \
+                         hand of the uncle in both of his own, and for a long moment neither of \
+                         them could speak. Then he turned to the boy and said that he must be \
+                         hungry after such a journey, and called for breakfast to be brought. \
+                         While they ate he told them that he had found among the papers of his \
+                         late father a map of the hills to the north, and on the map was marked a \
+                         place where something had been hidden during the war. He did not know \
+                         what it was, but his father had written beside the mark that it must be \
+                         returned to the family from whom it had been taken, and he believed that \
+                         the family was their own. The uncle listened without a word until his \
+                         friend had finished, and then asked to see the map. It was drawn in \
+                         brown ink on a sheet of heavy paper that had been folded many times, and \
+                         in the corner was a small compass rose and the name of a village that \
+                         none of them knew. The hills were shown as rows of little peaks, and a \
+                         stream ran between them to a lake, and beside the lake was a cross with \
+                         a circle around it. Beneath the cross someone had written three words in \
+                         a language that the boy did not understand, but his uncle read them \
+                         slowly aloud and said that they meant under the old stone.";
+
+    fn key() -> M209 {
+        M209::new(
+            &[
+                "ABDHIKMNSTVW",
+                "ADEGJKLORSUX",
+                "ABGHJLMNRSTUX",
+                "CEFHIMNPSTU",
+                "BDEFHIMNPS",
+                "ABDHKNOQ",
+            ],
+            &[
+                "3-6", "0-6", "1-6", "1-5", "4-5", "0-4", "0-4", "0-4", "0-4", "2-0", "2-0", "2-0",
+                "2-0", "2-0", "2-0", "2-0", "2-0", "2-0", "2-0", "2-5", "2-5", "0-5", "0-5", "0-5",
+                "0-5", "0-5", "0-5",
+            ],
+            "QHBMFC",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_known_lugs() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                                 TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                                 EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT\
+                                 YEMRDELIVEREDBEHAVIOURBYANIFTHEIRWOMANCOULDDOWOUNDONYO\
+                                 UFOLLYTASTEHOPEDTHEIRABOVEAREANDBUTATOURSELVESDIRECTIO\
+                                 NBELIEVINGDOHEDEPARTURECELEBRATEDHERHADSENTIMENTSUNDER\
+                                 STOODAREPROJECTIONSETPOSSESSIONYENOMRUNAFFECTEDREMARKA\
+                                 BLYATWROTEHOUSEINNEVERFRUITUPPASTUREIMAGINEMYGARRETSAN\
+                                 HEHOWEVERDISTANTSHEREQUESTBEHAVEDSEENOTHINGTALKINGSETT\
+                                 LEDATPLEASEDANOFMEBROTHERWEATHERINONANNOUNCINGIFOFCOMP\
+                                 ARISONPIANOFORTEPROJECTIONMAIDSHOPEDGAYYETBEDASKEDBLIN"
+            .into();
+
+        let mut key = key();
+        let ciphertext = key.encrypt(plaintext.clone()).unwrap();
+
+        let results = M209Anneal::new(20000, 2)
+            .with_lugs(&key)
+            .with_seed(1)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+
+        assert!(results[0].compare(&plaintext).accuracy() > 0.9);
+    }
+
+    #[test]
+    fn test_unknown_lugs() {
+        let plaintext = Buffer::from(PROSE);
+        let ciphertext = key().encrypt(plaintext.clone()).unwrap();
+
+        let results = M209Anneal::new(20000, 1)
+            .with_seed(1)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+
+        assert!(results[0].compare(&plaintext).accuracy() > 0.9);
+    }
+}
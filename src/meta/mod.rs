@@ -1,11 +1,29 @@
 use crate::ciphers::Decrypt;
+use crate::compare::{self, Comparison};
 use crate::score::{Score, ScoreMethod};
 use crate::Buffer;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{FromEntropy, Rng, SeedableRng};
 use std::ops::Index;
 
 pub mod brute;
+pub mod enigma;
 pub mod hillclimb;
+pub mod homophonic;
+pub mod jefferson;
+pub mod lorenz;
+pub mod m209;
+pub mod porta;
+pub mod progressive;
+pub mod quagmire;
+
+// The random number generator for a search, seeded when it needs to be repeatable
+pub(crate) fn search_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
 
 pub trait HeuristicTarget: Decrypt + Sized + Clone + PartialEq {
     type KeyParam: Copy; // This might be a key length, range of key lengths, matrix size etc. Differs per cipher
@@ -20,13 +38,20 @@ pub trait HeuristicTarget: Decrypt + Sized + Clone + PartialEq {
 }
 
 // TODO: Display?
-pub struct CrackResult<K: HeuristicTarget> {
+pub struct CrackResult<K> {
     pub score: Score,
     pub buf: Buffer,
     pub key: K,
 }
 
-pub struct CrackResults<K: HeuristicTarget> {
+impl<K> CrackResult<K> {
+    // How close the result is to the plaintext, where that is known
+    pub fn compare(&self, plaintext: &Buffer) -> Comparison {
+        compare::compare(&self.buf, plaintext)
+    }
+}
+
+pub struct CrackResults<K> {
     data: Vec<CrackResult<K>>,
     results: usize,
 }
@@ -41,7 +66,7 @@ pub trait Metaheuristic {
     ) -> Result<CrackResults<T>, <T as Decrypt>::Error>;
 }
 
-impl<K: HeuristicTarget> CrackResults<K> {
+impl<K: PartialEq> CrackResults<K> {
     pub fn new(num_results: usize) -> Self {
         assert!(num_results > 0, "num_results was zero");
 
@@ -54,11 +79,12 @@ impl<K: HeuristicTarget> CrackResults<K> {
     pub fn process_result(&mut self, buf: Buffer, key: K, method: ScoreMethod) -> Score {
         let score = buf.score(method);
 
-        let min_score = self
-            .data
-            .last()
-            .map(|cr| cr.score)
-            .unwrap_or(crate::score::MIN_SCORE);
+        // Anything goes until the results are full
+        let min_score = if self.data.len() < self.results {
+            crate::score::MIN_SCORE
+        } else {
+            self.data.last().map(|cr| cr.score).unwrap()
+        };
 
         if score > min_score {
             // Remove the lowest scoring item if we're out of space
@@ -88,7 +114,7 @@ impl<K: HeuristicTarget> CrackResults<K> {
     }
 }
 
-impl<'a, K: HeuristicTarget> IntoIterator for &'a CrackResults<K> {
+impl<'a, K> IntoIterator for &'a CrackResults<K> {
     type Item = &'a CrackResult<K>;
     type IntoIter = std::slice::Iter<'a, CrackResult<K>>;
 
@@ -97,9 +123,29 @@ impl<'a, K: HeuristicTarget> IntoIterator for &'a CrackResults<K> {
     }
 }
 
-impl<K: HeuristicTarget> Index<usize> for CrackResults<K> {
+impl<K> Index<usize> for CrackResults<K> {
     type Output = CrackResult<K>;
     fn index(&self, idx: usize) -> &Self::Output {
         &self.data[idx]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_result() {
+        let mut results = CrackResults::new(2);
+        let method = ScoreMethod::ChiSquared;
+
+        results.process_result("Attack the east gate at dawn".into(), 1, method);
+        // A worse result still makes the list while there is room
+        results.process_result("Qzqzx xqzjq".into(), 2, method);
+        // But once it is full, only a better one does
+        results.process_result("Qzqzx xqzjq".into(), 3, method);
+
+        let keys = results.into_iter().map(|r| r.key).collect::<Vec<_>>();
+        assert_eq!(vec![1, 2], keys);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::ciphers::{Decrypt, Porta};
+use crate::meta::CrackResults;
+use crate::score::ScoreMethod;
+use crate::{stats, Buffer, Char};
+use simple_error::SimpleError;
+
+// Rows of the Porta tableau
+const ROWS: u8 = Char::MAX / 2;
+
+// Solves a Porta cipher column by column. Each column is enciphered with one of only 13
+// half-alphabets, so every row is tried on every column and the one whose decryption best fits
+// English letter frequencies is kept. Short columns can fit the wrong row, so each column is
+// then tried again in every row against the quadgram score of the whole text, until no single
+// column improves. That leaves one candidate key per period.
+//
+// A key repeated twice decrypts the same as the key itself, so shorter periods are tried first
+// and win ties.
+pub struct PortaSolve {
+    max_period: usize,
+    period: Option<usize>,
+}
+
+impl PortaSolve {
+    pub fn new(max_period: usize) -> Self {
+        assert!(max_period > 0, "max_period was zero");
+
+        Self {
+            max_period,
+            period: None,
+        }
+    }
+
+    pub fn with_period(mut self, period: usize) -> Self {
+        assert!(period > 0, "period was zero");
+
+        self.period = Some(period);
+        self
+    }
+
+    pub fn crack_ciphertext(
+        &mut self,
+        text: &Buffer,
+        num_results: usize,
+    ) -> Result<CrackResults<Porta>, SimpleError> {
+        if text.len() < 4 {
+            return Err(SimpleError::new("ciphertext is too short"));
+        }
+
+        let periods = match self.period {
+            Some(period) => period..=period,
+            None => 1..=self.max_period.min(text.len()),
+        };
+
+        let mut results = CrackResults::new(num_results);
+
+        for period in periods {
+            let rows = text
+                .columns(period)
+                .iter()
+                .map(best_row)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let rows = settle_columns(text, rows)?;
+
+            let mut key = Porta::from_rows(&rows);
+            let buf = key.decrypt(text.clone())?;
+            results.process_result(buf, key, ScoreMethod::Quadgrams);
+        }
+
+        Ok(results)
+    }
+}
+
+// The row whose decryption of a column has the lowest chi squared against English
+fn best_row(column: &Buffer) -> Result<u8, SimpleError> {
+    let (mut best, mut best_fit) = (0, f64::MAX);
+
+    for row in 0..ROWS {
+        let buf = Porta::from_rows(&[row]).decrypt(column.clone())?;
+        let fit = stats::chi_squared(&buf);
+
+        if fit < best_fit {
+            best = row;
+            best_fit = fit;
+        }
+    }
+
+    Ok(best)
+}
+
+fn settle_columns(text: &Buffer, mut rows: Vec<u8>) -> Result<Vec<u8>, SimpleError> {
+    let score = |rows: &[u8]| -> Result<f64, SimpleError> {
+        let buf = Porta::from_rows(rows).decrypt(text.clone())?;
+        Ok(stats::quadgram_score(&buf))
+    };
+
+    let mut best_score = score(&rows)?;
+    let mut improved = true;
+
+    while improved {
+        improved = false;
+
+        for i in 0..rows.len() {
+            let current = rows[i];
+
+            for row in (0..ROWS).filter(|&r| r != current) {
+                let mut next = rows.clone();
+                next[i] = row;
+
+                let s = score(&next)?;
+                if s > best_score {
+                    best_score = s;
+                    rows = next;
+                    improved = true;
+                }
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphers::Encrypt;
+
+    #[test]
+    fn test_solve() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                                 TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                                 EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT"
+            .into();
+        let mut key = Porta::new("PORTCULLIS").unwrap();
+        let ciphertext = key.encrypt(plaintext.clone()).unwrap();
+
+        let results = PortaSolve::new(12)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+
+        assert_eq!(results[0].buf, plaintext);
+        assert_eq!(key, results[0].key);
+
+        let results = PortaSolve::new(12)
+            .with_period(10)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+        assert_eq!(key, results[0].key);
+    }
+}
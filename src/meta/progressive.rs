@@ -0,0 +1,126 @@
+use crate::ciphers::{Decrypt, ProgressiveKey};
+use crate::meta::CrackResults;
+use crate::score::ScoreMethod;
+use crate::{stats, Buffer, Char};
+use simple_error::SimpleError;
+
+const LEN: usize = Char::MAX as usize;
+
+// Solves a progressive key cipher by trying every period and progression. Once the progression
+// is taken off each column is a Caesar shift, which is found from its letter frequencies, so
+// there are only 26 candidate keys per period to score.
+//
+// A key of period n with progression p decrypts the same as one of period 2n with progression
+// 2p, so shorter periods are tried first and win ties.
+pub struct ProgressiveSolve {
+    max_period: usize,
+    period: Option<usize>,
+}
+
+impl ProgressiveSolve {
+    pub fn new(max_period: usize) -> Self {
+        assert!(max_period > 0, "max_period was zero");
+
+        Self {
+            max_period,
+            period: None,
+        }
+    }
+
+    pub fn with_period(mut self, period: usize) -> Self {
+        assert!(period > 0, "period was zero");
+
+        self.period = Some(period);
+        self
+    }
+
+    pub fn crack_ciphertext(
+        &mut self,
+        text: &Buffer,
+        num_results: usize,
+    ) -> Result<CrackResults<ProgressiveKey>, SimpleError> {
+        if text.len() < 4 {
+            return Err(SimpleError::new("ciphertext is too short"));
+        }
+
+        let periods = match self.period {
+            Some(period) => period..=period,
+            None => 1..=self.max_period.min(text.len()),
+        };
+        let ciphertext = text.into_iter().map(|&c| u8::from(c)).collect::<Vec<_>>();
+
+        let mut log_freqs = [0.0; LEN];
+        for (l, f) in log_freqs.iter_mut().zip(stats::ENGLISH_FREQUENCIES.iter()) {
+            *l = f.ln();
+        }
+
+        let mut results = CrackResults::new(num_results);
+
+        for period in periods {
+            for progression in 0..Char::MAX {
+                let mut counts = vec![[0; LEN]; period];
+                for (i, &c) in ciphertext.iter().enumerate() {
+                    let progress = ((i / period) * usize::from(progression)) % LEN;
+                    counts[i % period][(usize::from(c) + LEN - progress) % LEN] += 1;
+                }
+
+                let shifts = counts
+                    .iter()
+                    .map(|column| best_shift(column, &log_freqs))
+                    .collect::<Vec<_>>();
+
+                let mut key = ProgressiveKey::from_shifts(&shifts, progression);
+                let buf = key.decrypt(text.clone())?;
+                results.process_result(buf, key, ScoreMethod::Quadgrams);
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+// The Caesar shift whose decryption of a column best fits English letter frequencies
+fn best_shift(column: &[u32; LEN], log_freqs: &[f64; LEN]) -> u8 {
+    let fit = |shift: usize| {
+        column
+            .iter()
+            .enumerate()
+            .map(|(c, &n)| f64::from(n) * log_freqs[(c + LEN - shift) % LEN])
+            .sum::<f64>()
+    };
+
+    let (mut best, mut best_fit) = (0, f64::MIN);
+    for shift in 0..LEN {
+        let f = fit(shift);
+        if f > best_fit {
+            best = shift;
+            best_fit = f;
+        }
+    }
+
+    best as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ciphers::Encrypt;
+
+    #[test]
+    fn test_solve() {
+        let plaintext: Buffer = "SINGLONGHERWAYSIZEWAITEDENDMUTUALMISSEDMYSELFTHELITTLE\
+                                 SISTERONESOINPOINTEDORCHICKENCHEEREDNEITHERSPIRITSINVI\
+                                 TEDMARIANNEANDHIMLAUGHTERCIVILITYFORMERLYHANDSOMESEXUS\
+                                 EPROSPECTHENCEWEDOORSISGIVENRAPIDSCALEABOVEAMDIFFICULT"
+            .into();
+        let mut key = ProgressiveKey::new("CIPHER", 3).unwrap();
+        let ciphertext = key.encrypt(plaintext.clone()).unwrap();
+
+        let results = ProgressiveSolve::new(12)
+            .crack_ciphertext(&ciphertext, 1)
+            .unwrap();
+
+        assert_eq!(results[0].buf, plaintext);
+        assert_eq!(key, results[0].key);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::alphabet::Reading;
+use crate::{Buffer, Char, CharStream};
+use simple_error::SimpleError;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::{fmt, slice};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    Dot,
+    Dash,
+    // Written as 'x': one between letters, two between words
+    Separator,
+}
+
+const CODES: [&str; Char::MAX as usize] = [
+    ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---", "-.-", ".-..", "--",
+    "-.", "---", ".--.", "--.-", ".-.", "...", "-", "..-", "...-", ".--", "-..-", "-.--", "--..",
+];
+
+// A stream of dots, dashes and separators, the intermediate form of the Morse based ciphers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MorseBuffer {
+    data: Vec<Symbol>,
+}
+
+impl Symbol {
+    pub fn to_char(self) -> char {
+        match self {
+            Symbol::Dot => '.',
+            Symbol::Dash => '-',
+            Symbol::Separator => 'x',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Symbol> {
+        match c {
+            '.' => Some(Symbol::Dot),
+            '-' => Some(Symbol::Dash),
+            'x' | 'X' => Some(Symbol::Separator),
+            _ => None,
+        }
+    }
+}
+
+impl MorseBuffer {
+    pub fn new(data: Vec<Symbol>) -> Self {
+        Self { data }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Symbol] {
+        &self.data
+    }
+
+    pub fn push(&mut self, symbol: Symbol) {
+        self.data.push(symbol);
+    }
+
+    // Pads with separators until the length is a multiple of `n`
+    pub fn pad_to_multiple(&mut self, n: usize) {
+        while !self.data.len().is_multiple_of(n) {
+            self.data.push(Symbol::Separator);
+        }
+    }
+
+    // Word breaks are taken from whitespace in the buffer's original text, which is read with
+    // the buffer's alphabet to tell which characters stand for its letters
+    pub fn encode(buf: &Buffer) -> Result<Self, SimpleError> {
+        if !buf.alphabet().is_english() {
+            return Err(SimpleError::new("Morse code needs the English alphabet"));
+        }
+
+        let alphabet = buf.alphabet();
+        let mut out = MorseBuffer::default();
+        let mut letters = buf.into_iter();
+        let mut first = true;
+        let mut word_break = false;
+
+        for c in buf.original().chars() {
+            if c.is_whitespace() {
+                word_break = true;
+            }
+
+            let count = match alphabet.read(c) {
+                Ok(Reading::Symbols(symbols)) => symbols.len(),
+                _ => continue,
+            };
+
+            for &letter in (&mut letters).take(count) {
+                if !first {
+                    out.push(Symbol::Separator);
+
+                    if word_break {
+                        out.push(Symbol::Separator);
+                    }
+                }
+
+                out.data.extend(
+                    CODES[usize::from(u8::from(letter))]
+                        .chars()
+                        .filter_map(Symbol::from_char),
+                );
+
+                first = false;
+                word_break = false;
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn decode_with<F>(&self, mut invalid: F) -> Result<Buffer, SimpleError>
+    where
+        F: FnMut(&str) -> Result<Option<Char>, SimpleError>,
+    {
+        let mut data = Vec::new();
+        let mut original = String::new();
+        let mut code = String::new();
+        let mut separators = 0;
+
+        // The trailing separator flushes the last letter
+        for &symbol in self.data.iter().chain(Some(&Symbol::Separator)) {
+            if symbol != Symbol::Separator {
+                if separators >= 2 && !data.is_empty() {
+                    original.push(' ');
+                }
+
+                separators = 0;
+                code.push(symbol.to_char());
+                continue;
+            }
+
+            separators += 1;
+
+            if code.is_empty() {
+                continue;
+            }
+
+            let letter = match CODES.iter().position(|&c| c == code) {
+                Some(i) => Some(Char::from(i as u8)),
+                None => invalid(&code)?,
+            };
+
+            if let Some(letter) = letter {
+                data.push(letter);
+                original.push(char::from(letter));
+            }
+
+            code.clear();
+        }
+
+        Ok(Buffer::new(data, Arc::new(original)))
+    }
+
+    pub fn decode(&self) -> Result<Buffer, SimpleError> {
+        self.decode_with(|code| {
+            Err(SimpleError::new(format!(
+                "invalid morse code sequence {}",
+                code
+            )))
+        })
+    }
+
+    // Decodes invalid sequences as X, so that a wrong key still produces a text of similar
+    // length that can be scored
+    pub fn decode_lossy(&self) -> Buffer {
+        self.decode_with(|_| Ok(Some(Char::from('X')))).unwrap()
+    }
+}
+
+impl<'a> IntoIterator for &'a MorseBuffer {
+    type Item = &'a Symbol;
+    type IntoIter = slice::Iter<'a, Symbol>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+impl From<Vec<Symbol>> for MorseBuffer {
+    fn from(data: Vec<Symbol>) -> Self {
+        MorseBuffer::new(data)
+    }
+}
+
+impl FromStr for MorseBuffer {
+    type Err = SimpleError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        data.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                Symbol::from_char(c)
+                    .ok_or_else(|| SimpleError::new("string contains non-morse characters"))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(MorseBuffer::new)
+    }
+}
+
+impl From<&str> for MorseBuffer {
+    fn from(data: &str) -> Self {
+        MorseBuffer::from_str(data).unwrap()
+    }
+}
+
+impl fmt::Display for MorseBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for symbol in &self.data {
+            write!(f, "{}", symbol.to_char())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alphabet::Policy;
+    use crate::Alphabet;
+
+    #[test]
+    fn test_encode_decode() {
+        let morse = MorseBuffer::encode(&Buffer::from("Come at once")).unwrap();
+        assert_eq!("-.-.x---x--x.xx.-x-xx---x-.x-.-.x.", morse.to_string());
+
+        let buf = morse.decode().unwrap();
+        assert_eq!("COME AT ONCE", buf.to_string());
+    }
+
+    #[test]
+    fn test_policies() {
+        let english = (*Alphabet::english()).clone();
+
+        let keep = Arc::new(english.clone().with_policy(Policy::Keep));
+        let buf = Buffer::parse("Café au lait", keep).unwrap();
+        let morse = MorseBuffer::encode(&buf).unwrap();
+        assert_eq!("CAF AU LAIT", morse.decode().unwrap().to_string());
+
+        let transliterate = Arc::new(english.with_policy(Policy::Transliterate));
+        let buf = Buffer::parse("Große Straße", transliterate).unwrap();
+        let morse = MorseBuffer::encode(&buf).unwrap();
+        assert_eq!("GROSSE STRASSE", morse.decode().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let morse = MorseBuffer::from("......x.-xx");
+
+        assert!(morse.decode().is_err());
+        assert_eq!("XA", morse.decode_lossy().to_string());
+        assert!(MorseBuffer::from_str(".-a").is_err());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+        let buf = Buffer::parse("Come at 9", alphanumeric).unwrap();
+
+        assert!(MorseBuffer::encode(&buf).is_err());
+    }
+}
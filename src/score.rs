@@ -13,8 +13,8 @@ pub enum ScoreMethod {
 #[derive(Copy, Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Score(OrderedFloat<f64>);
 
-pub const MAX_SCORE: Score = Score(OrderedFloat(std::f64::INFINITY));
-pub const MIN_SCORE: Score = Score(OrderedFloat(std::f64::NEG_INFINITY));
+pub const MAX_SCORE: Score = Score(OrderedFloat(f64::INFINITY));
+pub const MIN_SCORE: Score = Score(OrderedFloat(f64::NEG_INFINITY));
 
 pub fn score(buf: &Buffer, heur: ScoreMethod) -> Score {
     match heur {
@@ -1,18 +1,25 @@
-use crate::{Buffer, Char, CharStream};
+use crate::{Buffer, Char, CharStream, TokenBuffer};
 use itertools::Itertools;
 use lazy_static::lazy_static;
 
+// Forces the embedded bytes to be aligned so that they can be read as f32s
+#[repr(C, align(4))]
+struct Aligned<T: ?Sized>(T);
+
+static QUADGRAM_DATA: &Aligned<[u8]> = &Aligned(*include_bytes!("../data/quadgram_scores.raw"));
+
 lazy_static! {
     static ref QUADGRAMS: &'static [f32] = {
-        let buf = include_bytes!("../data/quadgram_scores.raw");
-        // TODO: Fix potential alignment concern, maybe assert the alignment of buf.as_ptr() ?
+        let buf = &QUADGRAM_DATA.0;
+        assert_eq!(buf.len(), 26 * 26 * 26 * 26 * 4);
 
         unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const f32, 26 * 26 * 26 * 26) }
     };
 }
 
-pub fn letter_frequencies<'a, T: CharStream<'a>>(buf: &'a T) -> [u32; Char::MAX as usize] {
-    let mut out = [0; Char::MAX as usize];
+// The count of each symbol of the buffer's alphabet
+pub fn letter_frequencies<'a, T: CharStream<'a>>(buf: &'a T) -> Vec<u32> {
+    let mut out = vec![0; usize::from(buf.alphabet().size())];
 
     for &b in buf.iter() {
         out[u8::from(b) as usize] += 1;
@@ -21,21 +28,30 @@ pub fn letter_frequencies<'a, T: CharStream<'a>>(buf: &'a T) -> [u32; Char::MAX
     out
 }
 
+pub const ENGLISH_FREQUENCIES: [f64; Char::MAX as usize] = [
+    0.08167, 0.01492, 0.02782, 0.04253, 0.12702, 0.02228, 0.02015, 0.06094, 0.06966, 0.00153,
+    0.00772, 0.04025, 0.02406, 0.06749, 0.07507, 0.01929, 0.00095, 0.05987, 0.06327, 0.09056,
+    0.02758, 0.00978, 0.02360, 0.00150, 0.01974, 0.00074,
+];
+
+// Against the expected frequencies of the buffer's alphabet, which for English are
+// `ENGLISH_FREQUENCIES`
 pub fn chi_squared<'a, T: CharStream<'a>>(buf: &'a T) -> f64 {
-    let english_freqs = [
-        0.08167, 0.01492, 0.02782, 0.04253, 0.12702, 0.02228, 0.02015, 0.06094, 0.06966, 0.00153,
-        0.00772, 0.04025, 0.02406, 0.06749, 0.07507, 0.01929, 0.00095, 0.05987, 0.06327, 0.09056,
-        0.02758, 0.00978, 0.02360, 0.00150, 0.01974, 0.00074,
-    ];
+    chi(
+        &letter_frequencies(buf),
+        buf.len(),
+        buf.alphabet().frequencies(),
+    )
+}
 
-    let freqs = letter_frequencies(buf);
-    let len_f = buf.len() as f64;
+pub(crate) fn chi(freqs: &[u32], len: usize, expected: &[f64]) -> f64 {
+    let len_f = len as f64;
 
     freqs
         .iter()
         .enumerate()
         .map(|(i, &f)| {
-            let e_count = len_f * english_freqs[i];
+            let e_count = len_f * expected[i];
             let diff = f as f64 - e_count;
             (diff * diff) / e_count
         })
@@ -43,37 +59,90 @@ pub fn chi_squared<'a, T: CharStream<'a>>(buf: &'a T) -> f64 {
 }
 
 pub fn index_of_coincidence<'a, T: CharStream<'a>>(buf: &'a T) -> f64 {
-    let freqs = letter_frequencies(buf);
+    coincidence(
+        &letter_frequencies(buf),
+        buf.len(),
+        buf.alphabet().size().into(),
+    )
+}
+
+// The count of each distinct token, in the order of `TokenBuffer::symbols`
+pub fn token_frequencies(buf: &TokenBuffer) -> Vec<u32> {
+    let mut out = vec![0; buf.symbols().len()];
+
+    for &t in buf.as_slice() {
+        out[t] += 1;
+    }
+
+    out
+}
 
+// Tokens have no alphabet to say how many there could be, so this is normalised by the number
+// that appear
+pub fn token_index_of_coincidence(buf: &TokenBuffer) -> f64 {
+    coincidence(&token_frequencies(buf), buf.len(), buf.symbols().len())
+}
+
+pub(crate) fn coincidence(freqs: &[u32], len: usize, symbols: usize) -> f64 {
+    // Wide enough for the counts of a whole corpus
     let total = freqs
         .iter()
         .filter(|&f| *f > 0)
-        .map(|&f| f * (f - 1))
-        .sum::<u32>() as f64;
+        .map(|&f| u64::from(f) * u64::from(f - 1))
+        .sum::<u64>() as f64;
 
-    let len = buf.len();
-    let denominator = (len * (len - 1)) as f64 / Char::MAX as f64;
+    let denominator = (len * (len - 1)) as f64 / symbols as f64;
 
     total / denominator
 }
 
+// The quadgrams are English, so in other alphabets only runs of four English letters are scored
 pub fn quadgram_score(buf: &Buffer) -> f64 {
     let mut score = 0.0_f64;
-
-    for (c1, c2, c3, c4) in buf.into_iter().tuple_windows() {
-        let hash = (u8::from(*c1) as usize * 26_usize.pow(3))
-            + (u8::from(*c2) as usize * 26_usize.pow(2))
-            + (u8::from(*c3) as usize * 26_usize.pow(1))
-            + (u8::from(*c4) as usize * 26_usize.pow(0));
-        score += QUADGRAMS[hash] as f64; // TODO: Remove bounds checks
+    let alphabet = buf.alphabet();
+
+    if alphabet.is_english() {
+        for (c1, c2, c3, c4) in buf.into_iter().tuple_windows() {
+            score += quadgram(*c1, *c2, *c3, *c4);
+        }
+    } else {
+        for window in english_letters(buf).tuple_windows() {
+            if let (Some(c1), Some(c2), Some(c3), Some(c4)) = window {
+                score += quadgram(c1, c2, c3, c4);
+            }
+        }
     }
 
     score / (buf.len() as f64) // Normalise based on text length
 }
 
+// Each character as the English letter it stands for, if any, to look up in the quadgrams
+pub(crate) fn english_letters(buf: &Buffer) -> impl Iterator<Item = Option<Char>> + '_ {
+    let alphabet = buf.alphabet();
+
+    buf.into_iter().map(move |&c| {
+        let symbol = alphabet.symbol(c).to_ascii_uppercase();
+        Some(symbol)
+            .filter(char::is_ascii_uppercase)
+            .map(Char::from)
+    })
+}
+
+// Log probability of a single quadgram, for scoring texts incrementally
+pub fn quadgram(c1: Char, c2: Char, c3: Char, c4: Char) -> f64 {
+    let hash = (u8::from(c1) as usize * 26_usize.pow(3))
+        + (u8::from(c2) as usize * 26_usize.pow(2))
+        + (u8::from(c3) as usize * 26_usize.pow(1))
+        + (u8::from(c4) as usize * 26_usize.pow(0));
+
+    QUADGRAMS[hash] as f64 // TODO: Remove bounds checks
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::alphabet::{Alphabet, Merge};
+    use std::sync::Arc;
 
     #[test]
     fn test_heuristics() {
@@ -91,6 +160,38 @@ mod tests {
         let expected = [
             3, 1, 0, 0, 3, 0, 4, 1, 2, 0, 0, 1, 2, 2, 1, 1, 0, 3, 3, 3, 2, 0, 0, 0, 0, 0,
         ];
-        assert_eq!(expected, letter_frequencies(&buf));
+        assert_eq!(expected, &letter_frequencies(&buf)[..]);
+    }
+
+    #[test]
+    fn test_tokens() {
+        let buf = TokenBuffer::from("11 23 11 42 23 11");
+
+        assert_eq!(vec![3, 2, 1], token_frequencies(&buf));
+        assert_eq!(0.8, token_index_of_coincidence(&buf));
+    }
+
+    #[test]
+    fn test_other_alphabets() {
+        let alphanumeric = Arc::new(Alphabet::alphanumeric());
+
+        let buf = Buffer::parse("Rust 2018 is the best", alphanumeric.clone()).unwrap();
+        let freqs = letter_frequencies(&buf);
+        assert_eq!(36, freqs.len());
+        assert_eq!([1, 1, 1, 0, 0, 0, 0, 0, 1, 0], freqs[26..]);
+
+        // Text of letters alone scores the same in either alphabet
+        let text = "Rust is the best programming language";
+        let buf = Buffer::parse(text, alphanumeric).unwrap();
+        assert_eq!(quadgram_score(&Buffer::from(text)), quadgram_score(&buf));
+
+        // Merged letters are counted together
+        let merged = Arc::new(Alphabet::twenty_five(Merge::IJ));
+        let buf = Buffer::parse("Jack is in", merged.clone()).unwrap();
+        assert_eq!(25, letter_frequencies(&buf).len());
+        assert_eq!(3, letter_frequencies(&buf)[8]);
+
+        let buf = Buffer::parse(text, merged).unwrap();
+        assert_eq!(quadgram_score(&Buffer::from(text)), quadgram_score(&buf));
     }
 }
@@ -0,0 +1,280 @@
+use crate::ciphers::{Decrypt, Encrypt, Resumable};
+use crate::{stats, Alphabet, Buffer};
+use itertools::Itertools;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 16;
+
+// Reads text a chunk at a time, so that corpora too large to hold in memory can be worked
+// through. Each chunk is a `Buffer` of its own, keeping the layout of its part of the text, and
+// chunks only ever end between characters.
+//
+// It is not a `CharStream` itself, and cannot be one while that trait stays as it is: `iter` lends
+// out references to every letter for as long as the stream is borrowed, and `original` the whole
+// text as one `&str`, so nothing read could ever be let go. Each chunk is a `CharStream`, and the
+// functions below work through them, carrying what crosses a boundary, so that stats and the
+// `Resumable` ciphers run in memory bounded by the chunk size.
+pub struct CharReader<R: Read> {
+    reader: R,
+    alphabet: Arc<Alphabet>,
+    chunk_size: usize,
+    // Bytes of a character split by the end of the last read
+    pending: Vec<u8>,
+    done: bool,
+}
+
+fn invalid_data<E: Into<Box<dyn Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+impl<R: Read> CharReader<R> {
+    pub fn new(reader: R, alphabet: Arc<Alphabet>) -> Self {
+        Self {
+            reader,
+            alphabet,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            pending: Vec::new(),
+            done: false,
+        }
+    }
+
+    // The number of bytes read at a time
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size is zero");
+
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    fn read_chunk(&mut self) -> io::Result<Option<Buffer>> {
+        let mut bytes = std::mem::take(&mut self.pending);
+        let start = bytes.len();
+        bytes.resize(start + self.chunk_size, 0);
+
+        let n = loop {
+            match self.reader.read(&mut bytes[start..]) {
+                Ok(n) => break n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        bytes.truncate(start + n);
+
+        if n == 0 {
+            self.done = true;
+
+            if bytes.is_empty() {
+                return Ok(None);
+            }
+        }
+
+        // Holds back the start of a character cut off by the end of the read
+        let valid = match std::str::from_utf8(&bytes) {
+            Ok(_) => bytes.len(),
+            Err(e) if e.error_len().is_none() && !self.done => e.valid_up_to(),
+            Err(e) => return Err(invalid_data(e)),
+        };
+        self.pending = bytes.split_off(valid);
+
+        let text = String::from_utf8(bytes).map_err(invalid_data)?;
+        Buffer::parse(&text, self.alphabet.clone())
+            .map(Some)
+            .map_err(invalid_data)
+    }
+}
+
+impl<R: Read> Iterator for CharReader<R> {
+    type Item = io::Result<Buffer>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.read_chunk() {
+                // A read may stop short of a whole character
+                Ok(Some(buf)) if buf.original().is_empty() => continue,
+                Ok(Some(buf)) => return Some(Ok(buf)),
+                Ok(None) => return None,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// The count of each symbol of the alphabet over the whole text
+pub fn letter_frequencies<R: Read>(reader: CharReader<R>) -> io::Result<Vec<u32>> {
+    let mut out = vec![0; usize::from(reader.alphabet.size())];
+
+    for buf in reader {
+        for (total, n) in out.iter_mut().zip(stats::letter_frequencies(&buf?)) {
+            *total += n;
+        }
+    }
+
+    Ok(out)
+}
+
+// The same as `stats::index_of_coincidence` over the whole text
+pub fn index_of_coincidence<R: Read>(reader: CharReader<R>) -> io::Result<f64> {
+    let symbols = usize::from(reader.alphabet.size());
+    let freqs = letter_frequencies(reader)?;
+    let len = freqs.iter().map(|&f| f as usize).sum();
+
+    Ok(stats::coincidence(&freqs, len, symbols))
+}
+
+// The same as `stats::chi_squared` over the whole text
+pub fn chi_squared<R: Read>(reader: CharReader<R>) -> io::Result<f64> {
+    let alphabet = reader.alphabet.clone();
+    let freqs = letter_frequencies(reader)?;
+    let len = freqs.iter().map(|&f| f as usize).sum();
+
+    Ok(stats::chi(&freqs, len, alphabet.frequencies()))
+}
+
+// The same as `stats::quadgram_score` over the whole text, counting the quadgrams that straddle
+// two chunks
+pub fn quadgram_score<R: Read>(reader: CharReader<R>) -> io::Result<f64> {
+    let mut score = 0.0_f64;
+    let mut len = 0;
+    let mut tail = Vec::new();
+
+    for buf in reader {
+        let buf = buf?;
+        let letters = tail
+            .iter()
+            .cloned()
+            .chain(stats::english_letters(&buf))
+            .collect::<Vec<_>>();
+
+        for window in letters.iter().tuple_windows() {
+            if let (Some(c1), Some(c2), Some(c3), Some(c4)) = window {
+                score += stats::quadgram(*c1, *c2, *c3, *c4);
+            }
+        }
+
+        len += buf.len();
+        tail = letters[letters.len().saturating_sub(3)..].to_vec();
+    }
+
+    Ok(score / len as f64)
+}
+
+// Encrypts text a chunk at a time, writing each out in the layout it was read in
+pub fn encrypt<R, W, C>(reader: CharReader<R>, writer: &mut W, cipher: &mut C) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+    C: Encrypt + Resumable,
+{
+    for buf in reader {
+        let buf = buf?;
+        let len = buf.len();
+
+        let out = cipher
+            .encrypt(buf)
+            .map_err(|e| invalid_data(e.to_string()))?;
+        write!(writer, "{}", out)?;
+        cipher.skip(len);
+    }
+
+    Ok(())
+}
+
+pub fn decrypt<R, W, C>(reader: CharReader<R>, writer: &mut W, cipher: &mut C) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+    C: Decrypt + Resumable,
+{
+    for buf in reader {
+        let buf = buf?;
+        let len = buf.len();
+
+        let out = cipher
+            .decrypt(buf)
+            .map_err(|e| invalid_data(e.to_string()))?;
+        write!(writer, "{}", out)?;
+        cipher.skip(len);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alphabet::Policy;
+    use crate::ciphers::Vigenere;
+
+    const TEXT: &str = "Über den Wolken muss die Freiheit wohl grenzenlos sein";
+
+    #[test]
+    fn test_chunks() {
+        let alphabet = Alphabet::english().as_ref().clone();
+        let alphabet = Arc::new(alphabet.with_policy(Policy::Transliterate));
+
+        // Chunks of three bytes split the Ü, which is two
+        let reader = CharReader::new(TEXT.as_bytes(), alphabet.clone()).with_chunk_size(3);
+        let chunks = reader.collect::<io::Result<Vec<_>>>().unwrap();
+        assert!(chunks.len() > 10);
+
+        let text = chunks.iter().map(|b| b.to_string()).collect::<String>();
+        assert_eq!(TEXT, text);
+
+        let invalid = [b'A', 0xff, b'B'];
+        let mut reader = CharReader::new(&invalid[..], alphabet);
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_stats() {
+        let buf = Buffer::from(TEXT.replace('Ü', "U").as_str());
+        let alphabet = Alphabet::english().as_ref().clone();
+        let alphabet = Arc::new(alphabet.with_policy(Policy::Transliterate));
+
+        let reader = CharReader::new(TEXT.as_bytes(), alphabet.clone()).with_chunk_size(5);
+        assert_eq!(
+            stats::letter_frequencies(&buf),
+            letter_frequencies(reader).unwrap()
+        );
+
+        let reader = CharReader::new(TEXT.as_bytes(), alphabet.clone()).with_chunk_size(5);
+        let ioc = index_of_coincidence(reader).unwrap();
+        assert!((stats::index_of_coincidence(&buf) - ioc).abs() < 1e-9);
+
+        let reader = CharReader::new(TEXT.as_bytes(), alphabet.clone()).with_chunk_size(5);
+        let chi = chi_squared(reader).unwrap();
+        assert!((stats::chi_squared(&buf) - chi).abs() < 1e-9);
+
+        let reader = CharReader::new(TEXT.as_bytes(), alphabet).with_chunk_size(5);
+        let score = quadgram_score(reader).unwrap();
+        assert!((stats::quadgram_score(&buf) - score).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let text = "Hello world, and hello again";
+        let mut out = Vec::new();
+
+        let reader = CharReader::new(text.as_bytes(), Alphabet::english()).with_chunk_size(4);
+        encrypt(reader, &mut out, &mut Vigenere::new("KEY")).unwrap();
+
+        let expected = Vigenere::new("KEY").encrypt(Buffer::from(text)).unwrap();
+        assert_eq!(
+            expected.to_string(),
+            String::from_utf8(out.clone()).unwrap()
+        );
+
+        let mut plain = Vec::new();
+        let reader = CharReader::new(&out[..], Alphabet::english()).with_chunk_size(5);
+        decrypt(reader, &mut plain, &mut Vigenere::new("KEY")).unwrap();
+        assert_eq!(text, String::from_utf8(plain).unwrap());
+    }
+}
@@ -0,0 +1,174 @@
+use simple_error::SimpleError;
+use std::fmt;
+use std::str::FromStr;
+
+// A stream of tokens, for ciphertexts written in numbers or symbols rather than letters, such as
+// Polybius coordinates, homophones or the Zodiac's glyphs. Each distinct token is given an index
+// in the order it first appears, and the stream is held as those indices.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenBuffer {
+    data: Vec<usize>,
+    symbols: Vec<String>,
+}
+
+impl TokenBuffer {
+    pub fn new<T: AsRef<str>>(tokens: &[T]) -> Self {
+        let mut out = TokenBuffer::default();
+
+        for token in tokens {
+            out.push(token.as_ref());
+        }
+
+        out
+    }
+
+    // Tokens split by whitespace or commas, such as "23 5 101"
+    pub fn parse_delimited(data: &str) -> Self {
+        let tokens = data
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+
+        Self::new(&tokens)
+    }
+
+    // Groups of a fixed number of characters run together, such as "231142" read as pairs.
+    // Whitespace is ignored.
+    pub fn parse_fixed(data: &str, width: usize) -> Result<Self, SimpleError> {
+        assert!(width > 0, "width is zero");
+
+        let chars = data
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<Vec<_>>();
+
+        if chars.len() % width != 0 {
+            return Err(SimpleError::new(format!(
+                "text does not split into groups of {}",
+                width
+            )));
+        }
+
+        let tokens = chars
+            .chunks(width)
+            .map(|c| c.iter().collect::<String>())
+            .collect::<Vec<_>>();
+
+        Ok(Self::new(&tokens))
+    }
+
+    // Every character other than whitespace is a token of its own
+    pub fn parse_symbols(data: &str) -> Self {
+        let tokens = data
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        Self::new(&tokens)
+    }
+
+    pub fn from_numbers(numbers: &[u32]) -> Self {
+        let tokens = numbers.iter().map(u32::to_string).collect::<Vec<_>>();
+        Self::new(&tokens)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // The index of each token in the stream
+    pub fn as_slice(&self) -> &[usize] {
+        &self.data
+    }
+
+    // The distinct tokens, in the order they first appear
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    pub fn symbol(&self, index: usize) -> &str {
+        &self.symbols[index]
+    }
+
+    pub fn push(&mut self, token: &str) {
+        let index = match self.symbols.iter().position(|s| s == token) {
+            Some(index) => index,
+            None => {
+                self.symbols.push(token.to_owned());
+                self.symbols.len() - 1
+            }
+        };
+
+        self.data.push(index);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.data.iter().map(move |&i| self.symbol(i))
+    }
+
+    // Reads every token as a number
+    pub fn numbers(&self) -> Result<Vec<u32>, SimpleError> {
+        self.iter()
+            .map(|s| {
+                s.parse()
+                    .map_err(|_| SimpleError::new(format!("'{}' is not a number", s)))
+            })
+            .collect()
+    }
+}
+
+impl FromStr for TokenBuffer {
+    type Err = SimpleError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        Ok(TokenBuffer::parse_delimited(data))
+    }
+}
+
+impl From<&str> for TokenBuffer {
+    fn from(data: &str) -> Self {
+        TokenBuffer::parse_delimited(data)
+    }
+}
+
+impl fmt::Display for TokenBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, token) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", token)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let tokens = TokenBuffer::from("23 5,101  5\n23");
+        assert_eq!(5, tokens.len());
+        assert_eq!(vec![0, 1, 2, 1, 0], tokens.as_slice().to_vec());
+        assert_eq!("23 5 101 5 23", tokens.to_string());
+        assert_eq!(vec![23, 5, 101, 5, 23], tokens.numbers().unwrap());
+        assert_eq!(tokens, TokenBuffer::from_numbers(&[23, 5, 101, 5, 23]));
+
+        let tokens = TokenBuffer::parse_fixed("2311 42\n11", 2).unwrap();
+        assert_eq!("23 11 42 11", tokens.to_string());
+        assert!(TokenBuffer::parse_fixed("23114", 2).is_err());
+
+        let tokens = TokenBuffer::parse_symbols("△●△ ◇");
+        assert_eq!(vec!["△", "●", "◇"], tokens.symbols().to_vec());
+        assert!(tokens.numbers().is_err());
+    }
+}